@@ -1,9 +1,9 @@
-use std::{path::PathBuf, time::Duration};
+use std::{path::PathBuf, time::{Duration, Instant, SystemTime}};
 
 use crossterm::event;
 use ratatui::{backend::Backend, layout::Rect, text::{Line, Text}, widgets::{Block, Borders}};
 
-use super::{assembly::AssemblyLine, color_settings::{self, ColorSettings}, help::HelpLine, info_mode::InfoMode, log::LogLine, notification::NotificationLevel, popup_state::PopupState, run_command::Command, widgets::{logo::Logo, scrollbar::Scrollbar}};
+use super::{assembly::AssemblyLine, color_settings::{self, ColorSettings}, editor::PATCH_COMPILE_DELAY, help::HelpLine, info_mode::InfoMode, log::LogLine, strings::FoundString, template::AppliedTemplate, notification::NotificationLevel, popup_state::PopupState, project::Project, run_command::Command, widgets::{logo::Logo, scrollbar::Scrollbar}};
 
 use crate::{fuzzer::fuzzer::Fuzzer, headers::header::Header};
 
@@ -11,6 +11,7 @@ pub struct App<'a>
 {
     pub(super) path: PathBuf,
     pub(super) commands: Fuzzer,
+    pub(super) command_history: Vec<String>,
     pub(super) header: Header,
     pub(super) log: Vec<LogLine>,
     pub(super) help_list: Vec<HelpLine>,
@@ -28,10 +29,27 @@ pub struct App<'a>
     pub(super) text_last_byte_index: usize,
     pub(super) text_cursor: (usize, usize),
     pub(super) assembly_scroll: usize,
+    pub(super) highlighted_register: usize,
     pub(super) info_mode: InfoMode,
+    pub(super) template: Option<AppliedTemplate>,
+    pub(super) strings: Vec<FoundString>,
+    pub(super) strings_fuzzer: Fuzzer,
+    pub(super) strings_min_length: usize,
+    pub(super) selection_anchor: Option<usize>,
+    pub(super) selection_highlight: Option<(usize, usize)>,
+    pub(super) clipboard: Option<String>,
+    // set when a script runs without the user interface
+    pub(super) headless: bool,
+    pub(super) project: Project,
+    pub(super) write_symbols: bool,
+    pub(super) backup: bool,
+    pub(super) backup_created: bool,
+    pub(super) file_state: Option<(SystemTime, u64)>,
     pub(super) scroll: usize,
     pub(super) cursor: (u16, u16),
     pub(super) poll_time: Duration,
+    // the C source of the patch popup is compiled only once it stops changing
+    pub(super) patch_edit_time: Option<Instant>,
     pub(super) needs_to_exit: bool,
     pub(super) screen_size: (u16, u16),
 
@@ -79,6 +97,7 @@ impl <'a> App<'a>
         let color_settings = color_settings::ColorSettings::default();
         Self::print_loading_status(&color_settings, &format!("Opening {}...", file_path.to_string_lossy()), terminal)?;
         let canonical_path = file_path.canonicalize().map_err(|e| e.to_string())?;
+        let file_state = Self::get_file_state(&canonical_path);
         let data = std::fs::read(&canonical_path).map_err(|e| e.to_string())?;
         let screen_size = Self::get_size(terminal)?;
         let block_size = 8;
@@ -90,12 +109,15 @@ impl <'a> App<'a>
         let text_view = Self::bytes_to_styled_text(&color_settings, &data, block_size, blocks_per_row);
         let header = Header::parse_header(&data);
         Self::print_loading_status(&color_settings, "Disassembling executable...", terminal)?;
-        let (assembly_offsets, assembly_instructions) = Self::sections_from_bytes(&data, &header);
+        // the project is loaded first because it defines the data in the code sections
+        let project = Project::load(&Project::path_for(&canonical_path));
+        let (assembly_offsets, assembly_instructions) = Self::sections_from_bytes(&data, &header, project.as_ref().unwrap_or(&Project::default()));
         let commands = Fuzzer::new(Command::get_commands().as_slice());
         Self::print_loading_status(&color_settings, "Opening ui...", terminal)?;
-        Ok(App{
+        let mut app = App{
             path: canonical_path,
             commands,
+            command_history: Vec::new(),
             header,
             log: Vec::new(),
             help_list: Self::help_list(),
@@ -113,10 +135,25 @@ impl <'a> App<'a>
             text_last_byte_index: 0,
             text_cursor: (0,0),
             assembly_scroll: 0,
+            highlighted_register: 0,
             info_mode: InfoMode::Text,
+            template: None,
+            strings: Vec::new(),
+            strings_fuzzer: Fuzzer::new(&[]),
+            strings_min_length: 4,
+            selection_anchor: None,
+            selection_highlight: None,
+            clipboard: None,
+            headless: false,
+            project: project.clone().unwrap_or_default(),
+            write_symbols: false,
+            backup: true,
+            backup_created: false,
+            file_state,
             scroll: 0,
             cursor: (0,0),
             poll_time: Duration::from_millis(1000),
+            patch_edit_time: None,
             needs_to_exit: false,
             screen_size,
 
@@ -127,7 +164,15 @@ impl <'a> App<'a>
             vertical_margin,
             block_size,
             blocks_per_row,
-        })
+        };
+        app.color_relocations();
+        match project
+        {
+            Ok(project) if !project.is_empty() => app.log(NotificationLevel::Info, &format!("Loaded project file with {} labels, {} comments and {} bookmarks.", project.labels.len(), project.comments.len(), project.bookmarks.len())),
+            Ok(_) => {},
+            Err(e) => app.log(NotificationLevel::Error, &e),
+        }
+        Ok(app)
     }
 
     pub fn run<B: Backend>(&mut self, terminal: &mut ratatui::Terminal<B>) -> Result<(),Box<dyn std::error::Error>>
@@ -146,6 +191,11 @@ impl <'a> App<'a>
             {
                 self.log(NotificationLevel::Info, &format!("Section: {}", section));
             }
+            let relocations = self.header.get_relocations().len();
+            if relocations > 0
+            {
+                self.log(NotificationLevel::Info, &format!("Relocations: {}", relocations));
+            }
         }
         else
         {
@@ -159,7 +209,8 @@ impl <'a> App<'a>
 
         while self.needs_to_exit == false
         {
-            if event::poll(self.poll_time)?
+            let poll_time = self.patch_edit_time.map_or(self.poll_time, |time| PATCH_COMPILE_DELAY.saturating_sub(time.elapsed()));
+            if event::poll(poll_time)?
             {
                 while event::poll(Duration::from_millis(0))?
                 {
@@ -171,6 +222,7 @@ impl <'a> App<'a>
                     }
                 }
             }
+            self.compile_edited_patch();
 
             terminal.draw(|f| {
                 self.screen_size = (f.size().width, f.size().height);
@@ -219,9 +271,20 @@ impl <'a> App<'a>
                         let assembly_end_index = (assembly_start_index + f.size().height as usize - 2).min(self.assembly_instructions.len());
                         let assembly_subview_lines = &self.assembly_instructions[assembly_start_index..assembly_end_index];
                         let mut assembly_subview = Text::default();
-                        assembly_subview.lines.extend(assembly_subview_lines.iter().map(|x| x.to_line(&self.color_settings, self.get_cursor_position().global_byte_index, &self.header)));
+                        let highlighted_register = self.get_highlighted_register();
+                        assembly_subview.lines.extend(assembly_subview_lines.iter().map(|x| x.to_line(&self.color_settings, self.get_cursor_position().global_byte_index, &self.header, &self.project, highlighted_register)));
                         ratatui::widgets::Paragraph::new(assembly_subview)
                             .block(Block::default().title("Assembly View").borders(Borders::TOP | Borders::RIGHT))
+                    },
+                    InfoMode::Inspector =>
+                    {
+                        ratatui::widgets::Paragraph::new(self.inspector_view())
+                            .block(Block::default().title("Inspector View").borders(Borders::TOP | Borders::RIGHT))
+                    },
+                    InfoMode::Template =>
+                    {
+                        ratatui::widgets::Paragraph::new(self.template_view(f.size().height as usize - 2))
+                            .block(Block::default().title("Template View").borders(Borders::TOP | Borders::RIGHT))
                     }
                 };
 
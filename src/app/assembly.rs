@@ -1,11 +1,11 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{borrow::Cow, collections::{BTreeMap, HashMap}, rc::Rc};
 
-use iced_x86::{Formatter, Instruction, SymbolResolver};
-use ratatui::text::{Line, Span};
+use iced_x86::{FlowControl, FormatterOutput, FormatterTextKind, Instruction, NumberKind, OpKind, Register, SymbolResolver};
+use ratatui::{style::Style, text::{Line, Span}};
 
-use crate::asm::assembler::assemble;
+use crate::asm::{assembler::{assemble_with_diagnostics, AssemblerError, Diagnostic}, compiler::{compile_with_diagnostics, Language}, nop::nops, syntax::Syntax};
 
-use super::{app::App, color_settings::ColorSettings, notification::NotificationLevel};
+use super::{app::App, color_settings::ColorSettings, disassembly::Disassembly, notification::NotificationLevel, project::Project};
 
 use crate::headers::header::{Header, Section};
 
@@ -25,23 +25,56 @@ pub struct InstructionTag
     pub file_address: u64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataKind
+{
+    Byte,
+    Dword,
+    Qword,
+    String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataTag
+{
+    pub kind: DataKind,
+    pub bytes: Vec<u8>,
+    pub file_address: u64,
+    pub virtual_address: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AssemblyLine
 {
     Instruction(InstructionTag),
+    Data(DataTag),
     SectionTag(SectionTag)
 }
 
+// quotes and backslashes are left out so that the strings do not need to be escaped
+fn is_string_byte(byte: u8) -> bool
+{
+    (0x20..0x7F).contains(&byte) && byte != b'"' && byte != b'\\'
+}
+
+// data is shown as strings only if it is made of printable runs of at least 4 characters separated by NULs
+fn is_string_data(bytes: &[u8]) -> bool
+{
+    bytes.first().is_some_and(|byte| is_string_byte(*byte)) &&
+        bytes.split(|byte| *byte == 0).all(|run| run.is_empty() || (run.len() >= 4 && run.iter().all(|byte| is_string_byte(*byte))))
+}
+
 pub struct CustomSymbolResolver
 {
-    symbol_table: Rc<HashMap<u64, String>>
+    symbol_table: Rc<HashMap<u64, String>>,
+    labels: Rc<HashMap<u64, String>>
 }
 
 impl CustomSymbolResolver
 {
-    pub fn new(symbol_table: Rc<HashMap<u64, String>>) -> Self
+    pub fn new(symbol_table: Rc<HashMap<u64, String>>, labels: Rc<HashMap<u64, String>>) -> Self
     {
-        Self { symbol_table }
+        Self { symbol_table, labels }
     }
 }
 
@@ -50,10 +83,117 @@ impl SymbolResolver for CustomSymbolResolver
     fn symbol(
             &mut self, _instruction: &Instruction, _operand: u32, _instruction_operand: Option<u32>, address: u64, _address_size: u32,
         ) -> Option<iced_x86::SymbolResult<'_>> {
-        self.symbol_table.get(&address).map(|symbol| iced_x86::SymbolResult::with_string(address, symbol.clone()))
+        self.labels.get(&address)
+            .or_else(|| self.symbol_table.get(&address))
+            .map(|symbol| iced_x86::SymbolResult::with_string(address, symbol.clone()))
+    }
+}
+
+// collects the formatted instruction as spans styled by the kind of each piece of text
+pub struct StyledFormatterOutput<'b>
+{
+    color_settings: &'b ColorSettings,
+    mnemonic_style: Style,
+    highlighted_register: Option<Register>,
+    spans: Vec<Span<'static>>
+}
+
+impl <'b> StyledFormatterOutput<'b>
+{
+    pub fn new(color_settings: &'b ColorSettings, instruction: &Instruction, highlighted_register: Option<Register>) -> Self
+    {
+        let mnemonic_style = match (instruction.mnemonic(), instruction.flow_control())
+        {
+            (iced_x86::Mnemonic::Nop, _) => color_settings.assembly_nop,
+            (iced_x86::Mnemonic::INVALID, _) => color_settings.assembly_bad,
+            (_, FlowControl::Call | FlowControl::IndirectCall) => color_settings.assembly_call,
+            (_, FlowControl::UnconditionalBranch | FlowControl::IndirectBranch) => color_settings.assembly_jump,
+            (_, FlowControl::ConditionalBranch) => color_settings.assembly_conditional_jump,
+            (_, FlowControl::Return) => color_settings.assembly_return,
+            (_, FlowControl::Interrupt | FlowControl::Exception | FlowControl::XbeginXabortXend) => color_settings.assembly_interrupt,
+            (_, FlowControl::Next) => color_settings.assembly_default,
+        };
+        Self { color_settings, mnemonic_style, highlighted_register, spans: Vec::new() }
+    }
+
+    pub fn into_spans(self) -> Vec<Span<'static>>
+    {
+        self.spans
+    }
+
+    fn operand_style(&self, instruction: &Instruction, instruction_operand: Option<u32>) -> Style
+    {
+        match instruction_operand.map(|operand| instruction.op_kind(operand))
+        {
+            Some(OpKind::Memory) => self.color_settings.assembly_memory,
+            _ => self.color_settings.assembly_immediate,
+        }
     }
 }
 
+impl FormatterOutput for StyledFormatterOutput<'_>
+{
+    fn write(&mut self, text: &str, kind: FormatterTextKind)
+    {
+        let style = match kind
+        {
+            FormatterTextKind::Mnemonic | FormatterTextKind::Prefix => self.mnemonic_style,
+            FormatterTextKind::Register => self.color_settings.assembly_register,
+            FormatterTextKind::Number => self.color_settings.assembly_immediate,
+            FormatterTextKind::Keyword => self.color_settings.assembly_memory,
+            FormatterTextKind::Label | FormatterTextKind::Function | FormatterTextKind::Data => self.color_settings.assembly_symbol,
+            _ => self.color_settings.assembly_operands,
+        };
+        self.spans.push(Span::styled(text.to_string(), style));
+    }
+
+    fn write_number(&mut self, instruction: &Instruction, _operand: u32, instruction_operand: Option<u32>, text: &str, _value: u64, _number_kind: NumberKind, _kind: FormatterTextKind)
+    {
+        let style = self.operand_style(instruction, instruction_operand);
+        self.spans.push(Span::styled(text.to_string(), style));
+    }
+
+    fn write_register(&mut self, _instruction: &Instruction, _operand: u32, _instruction_operand: Option<u32>, text: &str, register: Register)
+    {
+        let style = if self.highlighted_register.is_some_and(|highlighted| highlighted == register.full_register())
+        {
+            self.color_settings.assembly_register_highlight
+        }
+        else
+        {
+            self.color_settings.assembly_register
+        };
+        self.spans.push(Span::styled(text.to_string(), style));
+    }
+}
+
+impl DataTag
+{
+    fn item_size(&self) -> usize
+    {
+        match self.kind
+        {
+            DataKind::Byte | DataKind::String => 1,
+            DataKind::Dword => 4,
+            DataKind::Qword => 8,
+        }
+    }
+
+    // little endian values of the items of the line
+    pub fn values(&self) -> Vec<u64>
+    {
+        self.bytes.chunks(self.item_size()).map(|item| item.iter().rev().fold(0, |value, byte| value << 8 | *byte as u64)).collect()
+    }
+
+    pub fn directive(&self, syntax: Syntax) -> &'static str
+    {
+        match self.kind
+        {
+            DataKind::String => syntax.string_directive(self.bytes.last() == Some(&0)),
+            _ => syntax.data_directive(self.item_size()),
+        }
+    }
+}
 
 impl AssemblyLine
 {
@@ -62,6 +202,7 @@ impl AssemblyLine
         match self
         {
             AssemblyLine::Instruction(instruction) => instruction.file_address,
+            AssemblyLine::Data(data) => data.file_address,
             AssemblyLine::SectionTag(section) => section.file_address,
         }
     }
@@ -71,17 +212,33 @@ impl AssemblyLine
         match self
         {
             AssemblyLine::Instruction(instruction) => instruction.instruction.ip(),
+            AssemblyLine::Data(data) => data.virtual_address,
             AssemblyLine::SectionTag(section) => section.virtual_address
         }
     }
 
-    pub fn to_line(&self, color_settings: &ColorSettings, current_byte_index: usize, header: &Header) -> Line
+    pub fn size(&self) -> usize
+    {
+        match self
+        {
+            AssemblyLine::Instruction(instruction) => instruction.instruction.len(),
+            AssemblyLine::Data(data) => data.bytes.len(),
+            AssemblyLine::SectionTag(section) => section.size,
+        }
+    }
+
+    pub fn to_line(&self, color_settings: &ColorSettings, current_byte_index: usize, header: &Header, project: &Project, highlighted_register: Option<Register>) -> Line
     {
         match self
         {
             AssemblyLine::Instruction(instruction) => {
                 let selected = current_byte_index >= instruction.file_address as usize && current_byte_index < instruction.file_address as usize + instruction.instruction.len();
-                App::instruction_to_line(color_settings, instruction, selected, header)
+                App::instruction_to_line(color_settings, instruction, selected, header, project, highlighted_register)
+            },
+            AssemblyLine::Data(data) =>
+            {
+                let selected = current_byte_index >= data.file_address as usize && current_byte_index < data.file_address as usize + data.bytes.len();
+                App::data_to_line(color_settings, data, selected, header, project)
             },
             AssemblyLine::SectionTag(section) => 
             {
@@ -113,18 +270,77 @@ impl <'a> App<'a>
         {
             return Vec::new();
         }
+        let labels = self.project.labels.iter().filter(|(_, label)| label.contains(filter)).map(|(address, label)| (*address, label.clone()));
         let symbol_table = self.header.get_symbols();
         if let Some(symbol_table) = symbol_table
         {
-            return symbol_table.iter().filter(|(_, symbol)| symbol.contains(filter)).map(|(address, symbol)| (*address, symbol.clone())).collect()
+            return labels.chain(symbol_table.iter().filter(|(_, symbol)| symbol.contains(filter)).map(|(address, symbol)| (*address, symbol.clone()))).collect()
         }
         else 
         {
-            return Vec::new();    
+            return labels.collect();    
         }
     }
 
-    fn instruction_to_line (color_settings: &ColorSettings, instruction: &InstructionTag, selected: bool, header: &Header) -> Line<'a>
+    fn get_symbol_resolver(header: &Header, labels: &Rc<HashMap<u64, String>>) -> Option<Box<dyn SymbolResolver>>
+    {
+        let symbol_table = header.get_symbols();
+        if symbol_table.is_some() || !labels.is_empty()
+        {
+            Some(Box::new(CustomSymbolResolver::new(symbol_table.unwrap_or_default(), labels.clone())))
+        }
+        else
+        {
+            None
+        }
+    }
+
+    pub(super) fn format_instruction(instruction: &Instruction, header: &Header, labels: &Rc<HashMap<u64, String>>, syntax: Syntax) -> String
+    {
+        let mut formatter = syntax.formatter(Self::get_symbol_resolver(header, labels));
+        let mut output = String::new();
+        formatter.format(instruction, &mut output);
+        output
+    }
+
+    // the registers read or written by the operands of the instruction, without repetitions
+    pub(super) fn get_instruction_registers(instruction: &Instruction) -> Vec<Register>
+    {
+        let mut registers = Vec::new();
+        for operand in 0..instruction.op_count()
+        {
+            let operand_registers = match instruction.op_kind(operand)
+            {
+                OpKind::Register => vec![instruction.op_register(operand)],
+                OpKind::Memory => vec![instruction.memory_base(), instruction.memory_index()],
+                _ => Vec::new(),
+            };
+            for register in operand_registers.into_iter().filter(|register| *register != Register::None)
+            {
+                if !registers.contains(&register.full_register())
+                {
+                    registers.push(register.full_register());
+                }
+            }
+        }
+        registers
+    }
+
+    // Tab cycles through the registers of the instruction at the cursor
+    pub(super) fn get_highlighted_register(&self) -> Option<Register>
+    {
+        match self.get_current_instruction()
+        {
+            AssemblyLine::Instruction(instruction) =>
+            {
+                let registers = Self::get_instruction_registers(&instruction.instruction);
+                registers.get(self.highlighted_register % registers.len().max(1)).copied()
+            },
+            AssemblyLine::Data(_) | AssemblyLine::SectionTag(_) => None,
+        }
+    }
+
+    fn instruction_to_line (color_settings: &ColorSettings, instruction: &InstructionTag, selected: bool, header: &Header, project: &Project, highlighted_register: Option<Register>) -> Line<'a>
     {
         let symbol_table = header.get_symbols();
         let mut line = Line::default();
@@ -141,52 +357,157 @@ impl <'a> App<'a>
         line.spans.push(Span::raw(" "));
         
 
-        let instruction_string = if let Some(symbol_table) = &symbol_table
-        {
-            let symbol_resolver = CustomSymbolResolver::new(symbol_table.clone());
-        let symbol_resolver_box = Box::new(symbol_resolver);
-            let mut formatter = iced_x86::NasmFormatter::with_options(Some(symbol_resolver_box),None);
-            let mut output = String::new();
-            formatter.format(&instruction.instruction, &mut output);
-            output
-        } 
-        else
+        let mut formatter = project.syntax.formatter(Self::get_symbol_resolver(header, &project.labels));
+        let mut output = StyledFormatterOutput::new(color_settings, &instruction.instruction, highlighted_register);
+        formatter.format(&instruction.instruction, &mut output);
+        line.spans.extend(output.into_spans());
+        // a label or a symbol inside the instruction means that the decoding is not aligned with the code
+        let ip = instruction.instruction.ip();
+        let inner_symbol = (ip + 1..ip + instruction.instruction.len() as u64).find_map(|address| project.labels.get(&address)
+            .or_else(|| symbol_table.as_ref().and_then(|symbol_table| symbol_table.get(&address))));
+        if let Some(symbol) = inner_symbol
         {
-            instruction.instruction.to_string()
-        };
-        let mut instruction_pieces = instruction_string.split_whitespace();
-        let mnemonic = instruction_pieces.next().unwrap().to_string();
-        let args = instruction_pieces.collect::<Vec<&str>>().join(" ");
-        let mnemonic_style = 
-        match instruction.instruction.mnemonic() {
-            iced_x86::Mnemonic::Nop => color_settings.assembly_nop,
-            iced_x86::Mnemonic::INVALID => color_settings.assembly_bad,
-            _ => color_settings.assembly_default,
-        };
-        
+            line.spans.push(Span::raw(" "));
+            line.spans.push(Span::styled(format!("misaligned <{}>", symbol), color_settings.assembly_bad));
+        }
+        Self::push_address_annotations(&mut line, color_settings, instruction.file_address, ip, header, project);
+        line
+    }
 
-        line.spans.push(Span::styled(mnemonic, mnemonic_style));
-        line.spans.push(Span::raw(" "));
-        line.spans.push(Span::raw(args));
-        if let Some(symbol_table) = symbol_table
+    // the label or the symbol, the entry point, the virtual address and the comment of a line
+    fn push_address_annotations(line: &mut Line, color_settings: &ColorSettings, file_address: u64, virtual_address: u64, header: &Header, project: &Project)
+    {
+        if let Some(label) = project.labels.get(&virtual_address)
+        {
+            line.spans.push(Span::raw(" "));
+            line.spans.push(Span::styled(format!("<{}>", label), color_settings.assembly_label));
+        }
+        else if let Some(symbol_table) = header.get_symbols()
         {
-            if let Some(symbol) = symbol_table.get(&instruction.instruction.ip())
+            if let Some(symbol) = symbol_table.get(&virtual_address)
             {
                 line.spans.push(Span::raw(" "));
                 line.spans.push(Span::styled(format!("<{}>", symbol), color_settings.assembly_symbol));
             }
         }
-        if instruction.instruction.ip() == header.entry_point()
+        if virtual_address == header.entry_point()
         {
             line.spans.push(Span::raw(" "));
             line.spans.push(Span::styled("EntryPoint", color_settings.assembly_entry_point));
         }
-        line.spans.push(Span::styled(format!(" @{:X}", instruction.instruction.ip()), color_settings.assembly_virtual_address));
+        line.spans.push(Span::styled(format!(" @{:X}", virtual_address), color_settings.assembly_virtual_address));
+        if let Some(comment) = project.comments.get(&file_address)
+        {
+            line.spans.push(Span::styled(format!(" ; {}", comment), color_settings.assembly_comment));
+        }
+    }
 
+    // the directive and its operands, pointers are replaced by the label or the symbol they point to
+    pub(super) fn data_to_spans(color_settings: &ColorSettings, data: &DataTag, header: &Header, project: &Project) -> Vec<Span<'static>>
+    {
+        let syntax = project.syntax;
+        let mut operands = Vec::new();
+        match data.kind
+        {
+            DataKind::String =>
+            {
+                let text = String::from_utf8_lossy(data.bytes.strip_suffix(&[0]).unwrap_or(&data.bytes)).to_string();
+                operands.push(Span::styled(format!("\"{}\"", text), color_settings.assembly_string));
+                // db does not add the terminator like .asciz
+                if data.bytes.last() == Some(&0) && data.directive(syntax) == syntax.data_directive(1)
+                {
+                    operands.push(Span::styled(syntax.format_number(0), color_settings.assembly_immediate));
+                }
+            },
+            DataKind::Byte => operands.extend(data.values().into_iter().map(|value| Span::styled(syntax.format_number(value), color_settings.assembly_immediate))),
+            DataKind::Dword | DataKind::Qword =>
+            {
+                let symbol_table = header.get_symbols();
+                for value in data.values()
+                {
+                    let symbol = project.labels.get(&value).or_else(|| symbol_table.as_ref().and_then(|symbol_table| symbol_table.get(&value)));
+                    operands.push(match symbol
+                    {
+                        Some(symbol) if value != 0 => Span::styled(symbol.clone(), color_settings.assembly_symbol),
+                        _ => Span::styled(syntax.format_number(value), color_settings.assembly_immediate),
+                    });
+                }
+            },
+        }
+        let mut spans = vec![Span::styled(format!("{} ", data.directive(syntax)), color_settings.assembly_data)];
+        for (i, operand) in operands.into_iter().enumerate()
+        {
+            if i > 0
+            {
+                spans.push(Span::styled(", ", color_settings.assembly_operands));
+            }
+            spans.push(operand);
+        }
+        spans
+    }
+
+    fn data_to_line(color_settings: &ColorSettings, data: &DataTag, selected: bool, header: &Header, project: &Project) -> Line<'a>
+    {
+        let mut line = Line::default();
+        let address_style = if selected { color_settings.assembly_selected } else { color_settings.assembly_address };
+        line.spans.push(Span::styled(format!("{:16X}", data.file_address), address_style));
+        line.spans.push(Span::raw(" "));
+        line.spans.extend(Self::data_to_spans(color_settings, data, header, project));
+        Self::push_address_annotations(&mut line, color_settings, data.file_address, data.virtual_address, header, project);
         line
     }
 
-    pub(super) fn sections_from_bytes(bytes: &[u8], header: &Header) -> (Vec<usize>, Vec<AssemblyLine>)
+    // strings are split after their NULs, numbers are shown as pointers if the range is aligned to their size
+    fn data_from_bytes(bytes: &[u8], bitness: u32, file_address: u64, virtual_address: u64) -> Vec<DataTag>
+    {
+        let mut items: Vec<(DataKind, &[u8])> = Vec::new();
+        if is_string_data(bytes)
+        {
+            let mut rest = bytes;
+            while !rest.is_empty()
+            {
+                let padding = rest.iter().take_while(|byte| **byte == 0).count();
+                let len = if padding > 0
+                {
+                    items.extend(rest[..padding].chunks(8).map(|chunk| (DataKind::Byte, chunk)));
+                    padding
+                }
+                else
+                {
+                    let len = rest.iter().position(|byte| *byte == 0).map(|index| index + 1).unwrap_or(rest.len());
+                    items.push((DataKind::String, &rest[..len]));
+                    len
+                };
+                rest = &rest[len..];
+            }
+        }
+        else
+        {
+            let aligned = |size: u64| (bytes.len() as u64).is_multiple_of(size) && virtual_address.is_multiple_of(size);
+            let (kind, size) = if bitness == 64 && aligned(8)
+            {
+                (DataKind::Qword, 8)
+            }
+            else if bitness != 16 && aligned(4)
+            {
+                (DataKind::Dword, 4)
+            }
+            else
+            {
+                (DataKind::Byte, 8)
+            };
+            items.extend(bytes.chunks(size).map(|chunk| (kind, chunk)));
+        }
+        let mut offset = 0;
+        items.into_iter().map(|(kind, item)|
+        {
+            let data = DataTag { kind, bytes: item.to_vec(), file_address: file_address + offset, virtual_address: virtual_address + offset };
+            offset += item.len() as u64;
+            data
+        }).collect()
+    }
+
+    pub(super) fn sections_from_bytes(bytes: &[u8], header: &Header, project: &Project) -> (Vec<usize>, Vec<AssemblyLine>)
     {
         let mut line_offsets = vec![0; bytes.len()];
         let mut lines = Vec::new();
@@ -200,6 +521,19 @@ impl <'a> App<'a>
                 size: bytes.len() as u64,
             });
         }
+        // added sections can be placed before tables that are listed earlier
+        sections.sort_by_key(|section| section.address);
+        let code_sections = header.get_code_sections();
+        let is_code = |section: &Section| section.name == ".text" || code_sections.contains(section);
+        let data_ranges = match project.disassembly
+        {
+            Disassembly::Linear => Cow::Borrowed(&project.data_ranges),
+            Disassembly::Recursive =>
+            {
+                let decoded_sections: Vec<Section> = sections.iter().filter(|section| is_code(section)).cloned().collect();
+                Cow::Owned(Self::get_recursive_data_ranges(bytes, header, project, &decoded_sections))
+            },
+        };
 
         let mut current_byte = 0;
         for section in sections
@@ -224,18 +558,18 @@ impl <'a> App<'a>
             current_byte = section.address as usize;
             match section.name.as_str()
             {
-                ".text" => {
+                name if is_code(&section) => {
                     lines.push(
                         AssemblyLine::SectionTag(
                             SectionTag {
-                                name: ".text".to_string(),
+                                name: name.to_string(),
                                 file_address: section.address,
                                 virtual_address: section.virtual_address,
                                 size: section.size as usize
                             }
                         )
                     );
-                    let (offsets, instructions) = Self::assembly_from_section(bytes, header, section.virtual_address as usize, current_byte, section.size as usize, lines.len());
+                    let (offsets, instructions) = Self::assembly_from_section(bytes, header, &data_ranges, section.virtual_address as usize, current_byte, section.size as usize, lines.len());
                     line_offsets.splice(section.address as usize..section.address as usize + section.size as usize, offsets);
                     lines.extend(instructions);
                     current_byte += section.size as usize;
@@ -277,87 +611,166 @@ impl <'a> App<'a>
         (line_offsets, lines)
     }
 
-    pub(super) fn assembly_from_section(bytes: &[u8], header: &Header, starting_ip: usize, starting_file_address: usize, section_size: usize, starting_sections: usize) -> (Vec<usize>, Vec<AssemblyLine>)
+    pub(super) fn assembly_from_section(bytes: &[u8], header: &Header, data_ranges: &BTreeMap<u64, u64>, starting_ip: usize, starting_file_address: usize, section_size: usize, starting_sections: usize) -> (Vec<usize>, Vec<AssemblyLine>)
     {
         let mut line_offsets = vec![0; section_size];
         let mut instructions = Vec::new();
+        let end = starting_file_address + section_size;
+        // the decoding starts again after each data range, so that the following instructions are aligned
+        let mut chunks = Vec::new();
+        let mut current_byte = starting_file_address;
+        for (start, len) in data_ranges.range(..end as u64)
+        {
+            let data_start = (*start as usize).max(current_byte);
+            let data_end = start.saturating_add(*len).min(end as u64) as usize;
+            if data_end > data_start
+            {
+                chunks.push((current_byte, data_start, false));
+                chunks.push((data_start, data_end, true));
+                current_byte = data_end;
+            }
+        }
+        chunks.push((current_byte, end, false));
+
         let mut current_byte = 0;
-        let mut decoder = iced_x86::Decoder::new(header.bitness(), &bytes[starting_file_address..starting_file_address + section_size], iced_x86::DecoderOptions::NONE);
-        decoder.set_ip(starting_ip as u64);
-        for instruction in decoder
+        for (from, to, is_data) in chunks.into_iter().filter(|(from, to, _)| from < to)
         {
-            let instruction_tag = InstructionTag
+            let ip = (starting_ip + from - starting_file_address) as u64;
+            let lines: Vec<AssemblyLine> = if is_data
             {
-                instruction,
-                file_address: current_byte as u64 + starting_file_address as u64
+                Self::data_from_bytes(&bytes[from..to], header.bitness(), from as u64, ip).into_iter().map(AssemblyLine::Data).collect()
+            }
+            else
+            {
+                iced_x86::Decoder::with_ip(header.bitness(), &bytes[from..to], ip, iced_x86::DecoderOptions::NONE).into_iter()
+                    .map(|instruction| AssemblyLine::Instruction(InstructionTag
+                    {
+                        instruction,
+                        file_address: instruction.ip() - ip + from as u64
+                    }))
+                    .collect()
             };
-            instructions.push(AssemblyLine::Instruction(instruction_tag));
-            for _ in 0..instruction.len()
+            for line in lines
             {
-                line_offsets[current_byte] = starting_sections + instructions.len() - 1;
-                current_byte += 1;
+                let size = line.size();
+                instructions.push(line);
+                for _ in 0..size
+                {
+                    line_offsets[current_byte] = starting_sections + instructions.len() - 1;
+                    current_byte += 1;
+                }
             }
         }
         (line_offsets, instructions)
     }
 
     pub(super) fn bytes_from_assembly(&self, assembly: &str, starting_virtual_address: u64) -> Result<Vec<u8>, String>
-    {        
-        let bytes = assemble(assembly, self.header.bitness(), starting_virtual_address);
-        match bytes
+    {
+        self.bytes_from_assembly_with_diagnostics(assembly, starting_virtual_address).0
+    }
+
+    pub(super) fn bytes_from_assembly_with_diagnostics(&self, assembly: &str, starting_virtual_address: u64) -> (Result<Vec<u8>, String>, Vec<Diagnostic>)
+    {
+        self.bytes_from_source_with_diagnostics(assembly, Language::Assembly, starting_virtual_address)
+    }
+
+    pub(super) fn bytes_from_source_with_diagnostics(&self, source: &str, language: Language, starting_virtual_address: u64) -> (Result<Vec<u8>, String>, Vec<Diagnostic>)
+    {
+        let symbols = Self::get_assembly_symbols(source, &self.header, &self.project.labels);
+        let result = match language
+        {
+            Language::Assembly => assemble_with_diagnostics(source, self.header.bitness(), starting_virtual_address, self.project.syntax, &symbols),
+            // 64 bit Windows code uses the Microsoft calling convention
+            Language::C => compile_with_diagnostics(source, self.header.bitness(), starting_virtual_address, matches!(self.header, Header::PE(_)), &symbols),
+        };
+        match result
         {
-            Ok(bytes) => Ok(bytes),
-            Err(e) => 
+            Ok((bytes, warnings)) => (Ok(bytes), warnings),
+            Err(e) =>
             {
-                Err(format!("{}", e.to_string()))
+                let diagnostics = e.downcast_ref::<AssemblerError>().map(|e| e.diagnostics.clone()).unwrap_or_default();
+                (Err(e.to_string()), diagnostics)
             },
         }
     }
 
-    pub(super) fn patch_bytes(&mut self, bytes: &[u8])
+    pub(super) fn get_patch_offset(&self) -> usize
     {
-        let current_instruction = self.get_current_instruction().clone();
-        let current_ip = match current_instruction
+        match self.get_current_instruction()
         {
-            AssemblyLine::Instruction(instruction) => instruction.file_address,
-            AssemblyLine::SectionTag(_) => self.get_cursor_position().global_byte_index as u64
-        };
-        for (i, byte) in bytes.iter().enumerate()
+            AssemblyLine::Instruction(instruction) => instruction.file_address as usize,
+            AssemblyLine::Data(_) | AssemblyLine::SectionTag(_) => self.get_cursor_position().global_byte_index
+        }
+    }
+
+    // original instructions that are overwritten, even partially, by a patch of len bytes at offset
+    pub(super) fn get_patched_instructions(&self, offset: usize, len: usize) -> Vec<InstructionTag>
+    {
+        if offset >= self.data.len()
         {
-            self.data[current_ip as usize + i] = *byte;
+            return Vec::new();
         }
-        self.color_instruction_bytes(&current_instruction, true);
-        for (i, byte) in bytes.iter().enumerate()
+        let instructions: Vec<InstructionTag> = self.assembly_instructions.iter().skip(self.assembly_offsets[offset])
+            .map_while(|line| match line
+            {
+                AssemblyLine::Instruction(instruction) => Some(*instruction),
+                AssemblyLine::Data(_) | AssemblyLine::SectionTag(_) => None,
+            })
+            .take_while(|instruction| (instruction.file_address as usize) < offset + len)
+            .collect();
+        if instructions.first().is_some_and(|instruction| instruction.file_address as usize == offset)
         {
-            let style = Self::get_style_for_byte(&self.color_settings, *byte);
-            let cursor_position = self.get_expected_cursor_position(current_ip as usize + i, true);
-            let [high_byte, low_byte] = Self::u8_to_hex(*byte);
+            instructions
+        }
+        else
+        {
+            Vec::new()
+        }
+    }
 
-            self.hex_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 3].content = high_byte.to_string().into();
-            self.hex_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 3].style = style;
-            self.hex_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 3 + 1].content = low_byte.to_string().into();
-            self.hex_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 3 + 1].style = style;
-            
-            self.text_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 2].content = Self::u8_to_char(*byte).to_string().into();
-            self.text_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 2].style = style;
+    // the patch is padded with NOPs up to the end of the last instruction it overwrites
+    pub(super) fn get_padded_patch(&self, offset: usize, bytes: &[u8]) -> Vec<u8>
+    {
+        let mut padded = bytes.to_vec();
+        if let Some(last) = self.get_patched_instructions(offset, bytes.len()).last()
+        {
+            let end = last.file_address as usize + last.instruction.len();
+            let padding = (end.min(self.data.len())).saturating_sub(offset + bytes.len());
+            padded.extend(nops(self.header.bitness(), padding));
         }
-        self.dirty = true;
-        self.edit_assembly(bytes.len());
-        self.update_cursors();
+        padded
     }
 
-    pub(super) fn patch(&mut self, assembly: &str)
+    // virtual address used to assemble the patch, the file offset is used if it is not mapped
+    pub(super) fn get_patch_virtual_address(&self) -> u64
     {
-        let current_instruction = self.get_current_instruction();
-        let current_virtual_address = if let AssemblyLine::Instruction(instruction) = current_instruction
+        match self.get_current_instruction()
         {
-            instruction.instruction.ip()
+            AssemblyLine::Instruction(instruction) => instruction.instruction.ip(),
+            AssemblyLine::Data(_) | AssemblyLine::SectionTag(_) =>
+            {
+                let offset = self.get_patch_offset() as u64;
+                self.header.physical_to_virtual_address(offset).unwrap_or(offset)
+            }
         }
-        else
+    }
+
+    pub(super) fn patch_bytes(&mut self, bytes: &[u8])
+    {
+        let current_ip = self.get_patch_offset();
+        let bytes = &self.get_padded_patch(current_ip, bytes);
+        let relocations = self.count_relocations_in(current_ip, current_ip + bytes.len());
+        if relocations > 0
         {
-            self.get_cursor_position().global_byte_index as u64
-        };
-        let bytes = self.bytes_from_assembly(assembly,current_virtual_address);
+            self.log(NotificationLevel::Warning, &format!("The patch overlaps {} relocations, the loader may overwrite the patched bytes", relocations));
+        }
+        self.write_bytes(current_ip, bytes);
+    }
+
+    pub(super) fn patch(&mut self, source: &str, language: Language)
+    {
+        let current_virtual_address = self.get_patch_virtual_address();
+        let (bytes, _) = self.bytes_from_source_with_diagnostics(source, language, current_virtual_address);
         match bytes
         {
             Ok(bytes) => self.patch_bytes(&bytes),
@@ -367,6 +780,69 @@ impl <'a> App<'a>
         }
     }
 
+    // a code cave is a run of zeros or int3 in a code section, the patch starts at a 16 byte aligned address
+    pub(super) fn find_code_cave(&self, len: usize) -> Option<usize>
+    {
+        for section in self.header.get_loaded_code_sections()
+        {
+            let start = section.address as usize;
+            let end = (start + section.size as usize).min(self.data.len());
+            let mut run_start = start;
+            for offset in start..end
+            {
+                let filler = self.data[offset] == 0x00 || self.data[offset] == 0xCC;
+                if !filler || self.data[offset] != self.data[run_start]
+                {
+                    run_start = offset + 1;
+                    continue;
+                }
+                // one byte of the run is left before the patch so that it does not extend the previous instruction
+                let virtual_address = section.virtual_address + (run_start + 1 - start) as u64;
+                let cave = run_start + 1 + (virtual_address.next_multiple_of(16) - virtual_address) as usize;
+                if cave + len <= offset + 1
+                {
+                    return Some(cave);
+                }
+            }
+        }
+        None
+    }
+
+    // the patch is assembled for the cursor first to know its size and then for the address of the cave
+    pub(super) fn patch_in_code_cave(&mut self, source: &str, language: Language)
+    {
+        let len = match self.bytes_from_source_with_diagnostics(source, language, self.get_patch_virtual_address()).0
+        {
+            Ok(bytes) => bytes.len(),
+            Err(e) =>
+            {
+                self.log(NotificationLevel::Error, &e);
+                return;
+            }
+        };
+        let cave = match self.find_code_cave(len)
+        {
+            Some(cave) => cave,
+            None =>
+            {
+                self.log(NotificationLevel::Error, &format!("No code cave of {} bytes found", len));
+                return;
+            }
+        };
+        let virtual_address = self.header.physical_to_virtual_address(cave as u64).unwrap_or(cave as u64);
+        match self.bytes_from_source_with_diagnostics(source, language, virtual_address).0
+        {
+            Ok(bytes) if bytes.len() <= len =>
+            {
+                self.jump_to(cave, false);
+                self.write_bytes(cave, &bytes);
+                self.log(NotificationLevel::Info, &format!("Patch of {} bytes written to the code cave at {:#X}", bytes.len(), virtual_address));
+            },
+            Ok(bytes) => self.log(NotificationLevel::Error, &format!("The patch is {} bytes long at {:#X}, more than the {} bytes reserved in the cave", bytes.len(), virtual_address, len)),
+            Err(e) => self.log(NotificationLevel::Error, &e),
+        }
+    }
+
     pub(super) fn update_assembly_scroll(&mut self)
     {
         // TODO: remove this function because it's useless
@@ -398,11 +874,35 @@ impl <'a> App<'a>
         &self.assembly_instructions[current_istruction_index as usize]
     }
 
-    pub(super) fn edit_assembly(&mut self, modifyied_bytes: usize)
+    // the assembly is decoded again starting from the instruction that contains offset
+    pub(super) fn edit_assembly(&mut self, offset: usize, len: usize)
     {
-        let from_byte = self.get_current_instruction().ip() as usize;
-        let virtual_address = self.get_current_instruction().virtual_ip();
-        let text_section = self.header.get_text_section();
+        if offset >= self.data.len()
+        {
+            return;
+        }
+        let from_byte = self.get_instruction_at(offset).ip() as usize;
+        let virtual_address = self.get_instruction_at(offset).virtual_ip();
+        let modifyied_bytes = offset + len - from_byte;
+        let edited_end = (from_byte + modifyied_bytes.max(1)) as u64;
+        let code_sections = self.header.get_code_sections();
+        // the recursive disassembly depends only on the bytes of the code sections and on the pointers in the data ranges
+        if self.project.disassembly == Disassembly::Recursive
+            && !code_sections.iter().any(|section| section.address < edited_end && section.address.saturating_add(section.size) > from_byte as u64)
+            && !self.project.data_ranges.range(..edited_end).any(|(start, len)| start.saturating_add(*len) > from_byte as u64)
+        {
+            return;
+        }
+        // the lines of a data range depend on all its bytes and the recursive disassembly on all the code, the whole file is decoded again
+        let modified_data = self.project.data_ranges.range(..edited_end).next_back()
+            .is_some_and(|(start, len)| start.saturating_add(*len) > from_byte as u64);
+        if modified_data || self.project.disassembly == Disassembly::Recursive
+        {
+            self.disassemble();
+            return;
+        }
+        let text_section = code_sections.iter().find(|section| from_byte >= section.address as usize && from_byte < section.address as usize + section.size as usize)
+            .cloned().or(self.header.get_text_section());
         let (is_inside_text_section, maximum_code_byte) = 
         if let Some(text_section) = text_section 
         {
@@ -418,12 +918,16 @@ impl <'a> App<'a>
         {
             return;
         }
+        // the decoding starts again after the next data range
+        let maximum_code_byte = self.project.data_ranges.range(from_byte as u64..).next()
+            .map_or(maximum_code_byte, |(start, _)| maximum_code_byte.min(*start as usize));
         let mut decoder = iced_x86::Decoder::new(self.header.bitness(), &self.data[from_byte..maximum_code_byte], iced_x86::DecoderOptions::NONE);
         decoder.set_ip(virtual_address as u64);
         let mut offsets = Vec::new();
         let mut instructions = Vec::new();
         let mut instruction_lines = Vec::new();
-        let mut to_byte = self.data.len();
+        // the decoding can stop at the end of the section without finding the original instructions again
+        let mut to_byte = maximum_code_byte;
 
         let from_instruction = self.assembly_offsets[from_byte];
         let mut current_byte = from_byte;
@@ -441,7 +945,7 @@ impl <'a> App<'a>
                 break;
             }
             instructions.push(AssemblyLine::Instruction(instruction_tag));
-            instruction_lines.push(Self::instruction_to_line(&self.color_settings, &instruction_tag, false, &self.header));
+            instruction_lines.push(Self::instruction_to_line(&self.color_settings, &instruction_tag, false, &self.header, &self.project, None));
             for _ in 0..instruction.len()
             {
                 offsets.push(from_instruction + instructions.len() - 1);
@@ -453,7 +957,7 @@ impl <'a> App<'a>
             return;
         }
 
-        let to_instruction = self.assembly_offsets[to_byte];
+        let to_instruction = self.assembly_offsets.get(to_byte).copied().unwrap_or(self.assembly_instructions.len());
 
         let mut original_instruction_count = 1;
         let mut original_instruction_ip = self.assembly_offsets[from_byte];
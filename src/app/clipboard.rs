@@ -0,0 +1,140 @@
+use std::io::{IsTerminal, Write};
+
+use ratatui::text::{Line, Span};
+
+use super::{assembly::AssemblyLine, color_settings::ColorSettings, notification::NotificationLevel, selection::{bytes_to_hex, parse_bytes}, App};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyItem
+{
+    FileOffset,
+    VirtualAddress,
+    Instruction,
+    InstructionBytes,
+    Selection,
+}
+
+impl CopyItem
+{
+    pub fn all() -> [CopyItem; 5]
+    {
+        [
+            CopyItem::FileOffset,
+            CopyItem::VirtualAddress,
+            CopyItem::Instruction,
+            CopyItem::InstructionBytes,
+            CopyItem::Selection,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            CopyItem::FileOffset => "offset",
+            CopyItem::VirtualAddress => "address",
+            CopyItem::Instruction => "instruction",
+            CopyItem::InstructionBytes => "instruction bytes",
+            CopyItem::Selection => "selection",
+        }
+    }
+}
+
+// OSC 52 asks the terminal to set the system clipboard, it works over ssh too
+fn osc52(text: &str) -> String
+{
+    format!("\x1b]52;c;{}\x07", super::selection::bytes_to_base64(text.as_bytes()))
+}
+
+impl <'a> App<'a>
+{
+    pub(super) fn get_copy_value(&self, item: CopyItem) -> Option<String>
+    {
+        let index = self.get_cursor_position().global_byte_index;
+        match item
+        {
+            CopyItem::FileOffset => Some(format!("{:#X}", index)),
+            CopyItem::VirtualAddress => self.header.physical_to_virtual_address(index as u64).map(|address| format!("{:#X}", address)),
+            CopyItem::Instruction => match self.get_current_instruction()
+            {
+                AssemblyLine::Instruction(instruction) => Some(Self::format_instruction(&instruction.instruction, &self.header, &self.project.labels, self.project.syntax)),
+                AssemblyLine::Data(data) => Some(Self::data_to_spans(&self.color_settings, data, &self.header, &self.project).iter().map(|span| span.content.as_ref()).collect()),
+                AssemblyLine::SectionTag(_) => None,
+            },
+            CopyItem::InstructionBytes => match self.get_current_instruction()
+            {
+                AssemblyLine::Instruction(instruction) =>
+                {
+                    let start = instruction.file_address as usize;
+                    Some(bytes_to_hex(&self.data[start..start + instruction.instruction.len()]))
+                },
+                AssemblyLine::Data(data) => Some(bytes_to_hex(&data.bytes)),
+                AssemblyLine::SectionTag(_) => None,
+            },
+            CopyItem::Selection =>
+            {
+                let (start, end) = self.get_selection();
+                Some(bytes_to_hex(&self.data[start..=end]))
+            },
+        }
+    }
+
+    pub(super) fn copy_item(&mut self, item: CopyItem)
+    {
+        match self.get_copy_value(item)
+        {
+            Some(value) => self.copy_to_clipboard(value, item.name()),
+            None => self.log(NotificationLevel::Error, &format!("No {} at the cursor", item.name())),
+        }
+    }
+
+    pub(super) fn copy_to_clipboard(&mut self, text: String, description: &str)
+    {
+        // the escape sequence is meaningful only to a terminal, and would end up in the output of a script
+        let mut stdout = std::io::stdout();
+        if !self.headless && stdout.is_terminal()
+        {
+            let result = stdout.write_all(osc52(&text).as_bytes()).and_then(|_| stdout.flush());
+            if let Err(e) = result
+            {
+                self.log(NotificationLevel::Warning, &format!("Could not copy to the system clipboard: {}", e));
+            }
+        }
+        self.log(NotificationLevel::Info, &format!("Copied {} characters as {}", text.len(), description));
+        self.clipboard = Some(text);
+    }
+
+    pub(super) fn paste_hex(&mut self, text: &str) -> Result<(), String>
+    {
+        let bytes = parse_bytes(text)?;
+        let offset = self.get_cursor_position().global_byte_index;
+        let len = bytes.len().min(self.data.len() - offset);
+        if len == 0
+        {
+            return Err("There are no bytes to paste".to_string());
+        }
+        // the bytes are written at once so that the assembly is decoded only once
+        self.write_bytes(offset, &bytes[..len]);
+        self.jump_to(offset + len - 1, false);
+        self.log(NotificationLevel::Info, &format!("Pasted {} bytes", len));
+        Ok(())
+    }
+
+    pub(super) fn copy_item_to_line(&self, color_settings: &ColorSettings, item: CopyItem, selected: bool, width: usize) -> Line<'a>
+    {
+        let (style_name, style_value) = if selected
+        {
+            (color_settings.menu_text_selected, color_settings.menu_text_selected)
+        }
+        else
+        {
+            (color_settings.command_name, color_settings.command_description)
+        };
+        let value = self.get_copy_value(item).unwrap_or_else(|| "-".to_string());
+        let value: String = value.chars().take(width.saturating_sub(20)).collect();
+        Line::from(vec![
+            Span::styled(format!("{:<18}", item.name()), style_name),
+            Span::styled(value, style_value),
+        ]).left_aligned()
+    }
+}
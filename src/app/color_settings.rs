@@ -15,6 +15,8 @@ pub struct ColorSettings
     pub hex_end_of_line: Style,
     pub hex_whitespace: Style,
     pub hex_current_instruction: Style,
+    pub hex_selection: Style,
+    pub hex_relocation: Style,
     pub hex_default: Style,
     pub current_instruction: Style,
 
@@ -30,11 +32,37 @@ pub struct ColorSettings
     pub assembly_entry_point: Style,
     pub assembly_default: Style,
     pub assembly_operands: Style,
+    pub assembly_label: Style,
+    pub assembly_comment: Style,
+    pub assembly_call: Style,
+    pub assembly_jump: Style,
+    pub assembly_conditional_jump: Style,
+    pub assembly_return: Style,
+    pub assembly_interrupt: Style,
+    pub assembly_register: Style,
+    pub assembly_register_highlight: Style,
+    pub assembly_immediate: Style,
+    pub assembly_memory: Style,
+    pub assembly_data: Style,
+    pub assembly_string: Style,
+
+    pub inspector_type: Style,
+    pub inspector_value: Style,
+
+    pub template_name: Style,
+    pub template_type: Style,
+    pub template_value: Style,
+    pub template_selected: Style,
+    pub template_field_even: Style,
+    pub template_field_odd: Style,
 
     pub patch_patched_less_or_equal: Style,
     pub patch_patched_greater: Style,
     pub patch_old_instruction: Style,
     pub patch_old_rest: Style,
+    pub patch_padding: Style,
+    pub patch_error_line: Style,
+    pub patch_warning_line: Style,
     pub patch_line_number: Style,
 
     pub help_command: Style,
@@ -85,6 +113,8 @@ impl Default for ColorSettings
             hex_end_of_line: Style::default().fg(Color::LightRed),
             hex_whitespace: Style::default().fg(Color::Rgb(244, 202, 183)),
             hex_current_instruction: Style::default().fg(Color::Rgb(0,0,0)).bg(Color::Rgb(215, 170, 92)),
+            hex_selection: Style::default().bg(Color::Rgb(70, 70, 120)),
+            hex_relocation: Style::default().add_modifier(Modifier::UNDERLINED),
             hex_default: Style::default(),
             current_instruction: Style::default().bg(Color::Rgb(244, 202, 183)).fg(Color::Rgb(0,0,0)),
 
@@ -100,11 +130,37 @@ impl Default for ColorSettings
             assembly_entry_point: Style::default().fg(Color::Yellow),
             assembly_default: Style::default().fg(Color::Rgb(204, 152, 113)),
             assembly_operands: Style::default(),
+            assembly_label: Style::default().fg(Color::LightMagenta),
+            assembly_comment: Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+            assembly_call: Style::default().fg(Color::LightBlue),
+            assembly_jump: Style::default().fg(Color::LightMagenta),
+            assembly_conditional_jump: Style::default().fg(Color::Magenta),
+            assembly_return: Style::default().fg(Color::LightRed),
+            assembly_interrupt: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            assembly_register: Style::default().fg(Color::LightCyan),
+            assembly_register_highlight: Style::default().fg(Color::Black).bg(Color::LightCyan),
+            assembly_immediate: Style::default().fg(Color::LightYellow),
+            assembly_memory: Style::default().fg(Color::Rgb(244, 202, 183)),
+            assembly_data: Style::default().fg(Color::LightBlue).add_modifier(Modifier::DIM),
+            assembly_string: Style::default().fg(Color::LightGreen).add_modifier(Modifier::ITALIC),
+
+            inspector_type: Style::default().fg(Color::DarkGray),
+            inspector_value: Style::default().fg(Color::Rgb(204, 152, 113)),
+
+            template_name: Style::default().fg(Color::LightBlue),
+            template_type: Style::default().fg(Color::DarkGray),
+            template_value: Style::default().fg(Color::Rgb(204, 152, 113)),
+            template_selected: Style::default().fg(Color::Black).bg(Color::White),
+            template_field_even: Style::default().bg(Color::Rgb(40, 44, 64)),
+            template_field_odd: Style::default().bg(Color::Rgb(64, 44, 40)),
 
             patch_patched_less_or_equal: Style::default().fg(Color::Green),
             patch_patched_greater: Style::default().fg(Color::Yellow),
             patch_old_instruction: Style::default().fg(Color::Red),
             patch_old_rest: Style::default().fg(Color::DarkGray),
+            patch_padding: Style::default().fg(Color::Green).add_modifier(Modifier::DIM),
+            patch_error_line: Style::default().add_modifier(Modifier::UNDERLINED).underline_color(Color::Red),
+            patch_warning_line: Style::default().add_modifier(Modifier::UNDERLINED).underline_color(Color::Yellow),
             patch_line_number: Style::default().fg(Color::DarkGray),
 
             help_command: Style::default().fg(Color::LightGreen),
@@ -162,4 +218,32 @@ impl <'a> App<'a>
             _ => color_settings.hex_default
         }
     }
+
+    pub(super) fn get_style_for_byte_at(&self, index: usize) -> Style
+    {
+        let mut style = Self::get_style_for_byte(&self.color_settings, self.data[index]);
+        if !self.header.relocations_in(index as u64, index as u64 + 1).is_empty()
+        {
+            style = style.patch(self.color_settings.hex_relocation);
+        }
+        if self.is_selected(index)
+        {
+            return style.patch(self.color_settings.hex_selection);
+        }
+        if let Some(template) = &self.template
+        {
+            if let Some(leaf) = template.leaf_at(index)
+            {
+                return style.patch(if leaf % 2 == 0
+                {
+                    self.color_settings.template_field_even
+                }
+                else
+                {
+                    self.color_settings.template_field_odd
+                });
+            }
+        }
+        style
+    }
 }
@@ -138,7 +138,12 @@ impl <'a> App<'a>
         }
         else
         {
-            if let Some(address) = self.header.symbol_to_address(symbol)
+            if let Some(address) = self.project.label_to_address(symbol)
+            {
+                self.log(NotificationLevel::Debug, &format!("Jumping to label {} at {:#X}", symbol, address));
+                self.jump_to(address as usize, true);
+            }
+            else if let Some(address) = self.header.symbol_to_address(symbol)
             {
                 self.log(NotificationLevel::Debug, &format!("Jumping to symbol {} at {:#X}", symbol, address));
                 self.jump_to(address as usize, true);
@@ -307,6 +312,7 @@ impl <'a> App<'a>
 
     pub(super) fn update_cursors(&mut self)
     {
+        self.update_selection_highlight();
         self.update_address_cursor();
         self.update_hex_cursor();
         self.update_text_cursor();
@@ -0,0 +1,59 @@
+use super::{assembly::AssemblyLine, disassembly::Disassembly, notification::NotificationLevel, App};
+
+impl <'a> App<'a>
+{
+    // the data ranges and the disassembly mode change the decoding of the rest of the section, everything is decoded again
+    pub(super) fn disassemble(&mut self)
+    {
+        let current_instruction = self.get_current_instruction().clone();
+        self.color_instruction_bytes(&current_instruction, true);
+        let (assembly_offsets, assembly_instructions) = Self::sections_from_bytes(&self.data, &self.header, &self.project);
+        self.assembly_offsets = assembly_offsets;
+        self.assembly_instructions = assembly_instructions;
+        self.update_cursors();
+    }
+
+    // the selection, or the instruction at the cursor, becomes data; if it starts inside data it becomes code instead,
+    // in the recursive disassembly the decoding also starts from there
+    pub(super) fn toggle_data_at_cursor(&mut self) -> Result<(), String>
+    {
+        let cursor = self.get_cursor_position().global_byte_index as u64;
+        let (from, to) = if self.selection_anchor.is_some()
+        {
+            let (start, end) = self.get_selection();
+            (start as u64, end as u64 + 1)
+        }
+        else
+        {
+            match self.get_current_instruction()
+            {
+                AssemblyLine::Instruction(instruction) => (instruction.file_address, instruction.file_address + instruction.instruction.len() as u64),
+                AssemblyLine::Data(_) => self.project.data_range_at(cursor).map(|(start, len)| (start, start.saturating_add(len))).unwrap_or((cursor, cursor + 1)),
+                AssemblyLine::SectionTag(_) => return Err("Data can be defined only in code sections".to_string()),
+            }
+        };
+        if let AssemblyLine::SectionTag(_) = self.get_instruction_at(from as usize)
+        {
+            return Err("Data can be defined only in code sections".to_string());
+        }
+        if let AssemblyLine::Data(_) = self.get_instruction_at(from as usize)
+        {
+            if self.project.disassembly == Disassembly::Recursive
+            {
+                self.project.code_starts.insert(from);
+            }
+            self.project.remove_data_range(from, to);
+            self.log(NotificationLevel::Info, &format!("Defined {} bytes at {:#X} as code", to - from, from));
+        }
+        else
+        {
+            self.project.add_data_range(from, to);
+            self.project.code_starts.retain(|start| *start < from || *start >= to);
+            self.log(NotificationLevel::Info, &format!("Defined {} bytes at {:#X} as data", to - from, from));
+        }
+        self.clear_selection();
+        self.disassemble();
+        self.save_project();
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+
+use iced_x86::{FlowControl, Instruction, Mnemonic, OpKind};
+use serde::{Deserialize, Serialize};
+
+use crate::headers::header::{Header, Section};
+
+use super::{project::Project, App};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Disassembly
+{
+    // the code sections are decoded from start to end
+    #[default]
+    Linear,
+    // only the code reached from the entry point, the symbols and the labels is decoded, the rest is data
+    Recursive,
+}
+
+impl Disassembly
+{
+    pub fn all() -> [Disassembly; 2]
+    {
+        [Disassembly::Linear, Disassembly::Recursive]
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Disassembly::Linear => "linear",
+            Disassembly::Recursive => "recursive",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Disassembly>
+    {
+        Self::all().into_iter().find(|disassembly| disassembly.name() == name.trim().to_lowercase())
+    }
+}
+
+// file offset of a virtual address and the end of its section, the sections whose bounds overflow are skipped
+fn to_file_offset(code_sections: &[Section], virtual_address: u64) -> Option<(usize, usize)>
+{
+    code_sections.iter()
+        .filter_map(|section| Some((section, section.virtual_address.checked_add(section.size)?, section.address.checked_add(section.size)?)))
+        .find(|(section, virtual_end, _)| virtual_address >= section.virtual_address && virtual_address < *virtual_end)
+        .map(|(section, _, end)| ((section.address + (virtual_address - section.virtual_address)) as usize, end as usize))
+}
+
+fn to_virtual_address(code_sections: &[Section], offset: u64) -> Option<u64>
+{
+    code_sections.iter()
+        .filter_map(|section| Some((section, section.address.checked_add(section.size)?, section.virtual_address.checked_add(section.size)?)))
+        .find(|(section, end, _)| offset >= section.address && offset < *end)
+        .map(|(section, _, _)| section.virtual_address + (offset - section.address))
+}
+
+// addresses loaded with lea or used as immediates are usually function pointers, like main passed to __libc_start_main
+fn get_code_pointers(instruction: &Instruction) -> Vec<u64>
+{
+    let mut pointers = Vec::new();
+    for operand in 0..instruction.op_count()
+    {
+        match instruction.op_kind(operand)
+        {
+            OpKind::Memory if instruction.mnemonic() == Mnemonic::Lea && instruction.is_ip_rel_memory_operand() =>
+                pointers.push(instruction.ip_rel_memory_address()),
+            OpKind::Immediate32 | OpKind::Immediate64 | OpKind::Immediate32to64 => pointers.push(instruction.immediate(operand)),
+            _ => {},
+        }
+    }
+    pointers
+}
+
+impl <'a> App<'a>
+{
+    // the entry point, the symbols, the labels, the starts defined by the user and the pointers in the data defined by the user
+    fn get_code_roots(bytes: &[u8], header: &Header, project: &Project, code_sections: &[Section]) -> Vec<u64>
+    {
+        let mut roots = vec![header.entry_point()];
+        if let Some(symbols) = header.get_symbols()
+        {
+            roots.extend(symbols.keys());
+        }
+        roots.extend(project.labels.keys());
+        roots.extend(project.code_starts.iter().filter_map(|offset| to_virtual_address(code_sections, *offset)));
+        // jump tables are found only once they are defined as data
+        let pointer_size = header.bitness() as usize / 8;
+        for (start, len) in project.data_ranges.iter()
+        {
+            let end = (start.saturating_add(*len) as usize).min(bytes.len());
+            for pointer in bytes[(*start as usize).min(end)..end].chunks_exact(pointer_size)
+            {
+                roots.push(pointer.iter().rev().fold(0, |value, byte| value << 8 | *byte as u64));
+            }
+        }
+        roots
+    }
+
+    // the branches and the calls are followed until a return, an indirect jump or an invalid instruction
+    fn get_reached_code(bytes: &[u8], header: &Header, project: &Project, code_sections: &[Section]) -> Vec<bool>
+    {
+        let mut reached = vec![false; bytes.len()];
+        let mut queue = Self::get_code_roots(bytes, header, project, code_sections);
+        while let Some(address) = queue.pop()
+        {
+            let (offset, end) = match to_file_offset(code_sections, address)
+            {
+                Some((offset, end)) if offset < bytes.len() && !reached[offset] => (offset, end.min(bytes.len())),
+                _ => continue,
+            };
+            let decoder = iced_x86::Decoder::with_ip(header.bitness(), &bytes[offset..end], address, iced_x86::DecoderOptions::NONE);
+            for instruction in decoder
+            {
+                let start = offset + (instruction.ip() - address) as usize;
+                if instruction.is_invalid() || reached[start]
+                {
+                    break;
+                }
+                reached[start..start + instruction.len()].fill(true);
+                queue.extend(get_code_pointers(&instruction).into_iter().filter(|pointer| to_file_offset(code_sections, *pointer).is_some()));
+                let branch_target = matches!(instruction.op0_kind(), OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64)
+                    .then(|| instruction.near_branch_target());
+                match instruction.flow_control()
+                {
+                    FlowControl::Next | FlowControl::IndirectCall => {},
+                    FlowControl::Call | FlowControl::ConditionalBranch | FlowControl::XbeginXabortXend => queue.extend(branch_target),
+                    FlowControl::UnconditionalBranch =>
+                    {
+                        queue.extend(branch_target);
+                        break;
+                    },
+                    FlowControl::Interrupt if instruction.mnemonic() != Mnemonic::Int3 => {},
+                    FlowControl::Interrupt | FlowControl::IndirectBranch | FlowControl::Return | FlowControl::Exception => break,
+                }
+            }
+        }
+        reached
+    }
+
+    // the bytes of the code sections that are not reached become data, the ranges defined by the user are kept apart
+    pub(super) fn get_recursive_data_ranges(bytes: &[u8], header: &Header, project: &Project, code_sections: &[Section]) -> BTreeMap<u64, u64>
+    {
+        let reached = Self::get_reached_code(bytes, header, project, code_sections);
+        let mut data_ranges = project.data_ranges.clone();
+        for section in code_sections
+        {
+            let end = section.address.saturating_add(section.size).min(bytes.len() as u64) as usize;
+            let mut offset = (section.address as usize).min(end);
+            while offset < end
+            {
+                if reached[offset]
+                {
+                    offset += 1;
+                    continue;
+                }
+                let run_end = reached[offset..end].iter().position(|reached| *reached).map_or(end, |len| offset + len);
+                let mut current = offset as u64;
+                for (start, len) in project.data_ranges.range(..run_end as u64)
+                {
+                    if *start > current
+                    {
+                        data_ranges.insert(current, start - current);
+                    }
+                    current = current.max(start.saturating_add(*len));
+                }
+                if current < run_end as u64
+                {
+                    data_ranges.insert(current, run_end as u64 - current);
+                }
+                offset = run_end;
+            }
+        }
+        data_ranges
+    }
+}
@@ -0,0 +1,377 @@
+use std::{sync::OnceLock, time::Duration};
+
+use crossterm::event::{self, KeyCode, KeyModifiers};
+use iced_x86::{Mnemonic, Register};
+use ratatui::style::Style;
+
+use crate::asm::compiler::Language;
+
+use super::{color_settings::ColorSettings, notification::NotificationLevel, popup_state::PopupState, App};
+
+const INDENT: &str = "    ";
+pub(super) const PATCH_COMPILE_DELAY: Duration = Duration::from_millis(500);
+
+// the names are sorted so that they can be searched and completed
+fn mnemonics() -> &'static [String]
+{
+    static MNEMONICS: OnceLock<Vec<String>> = OnceLock::new();
+    MNEMONICS.get_or_init(||
+    {
+        let mut mnemonics: Vec<String> = Mnemonic::values()
+            .filter(|mnemonic| *mnemonic != Mnemonic::INVALID)
+            .map(|mnemonic| format!("{:?}", mnemonic).to_lowercase())
+            .collect();
+        mnemonics.sort();
+        mnemonics.dedup();
+        mnemonics
+    })
+}
+
+fn registers() -> &'static [String]
+{
+    static REGISTERS: OnceLock<Vec<String>> = OnceLock::new();
+    REGISTERS.get_or_init(||
+    {
+        let mut registers: Vec<String> = Register::values()
+            .filter(|register| *register != Register::None)
+            .map(|register| format!("{:?}", register).to_lowercase())
+            .filter(|register| !register.starts_with("dontuse"))
+            .collect();
+        registers.sort();
+        registers.dedup();
+        registers
+    })
+}
+
+pub(super) fn is_mnemonic(word: &str) -> bool
+{
+    let word = word.to_lowercase();
+    if mnemonics().binary_search(&word).is_ok()
+    {
+        return true;
+    }
+    // AT&T mnemonics can have an operand size suffix
+    word.strip_suffix(['b', 'w', 'l', 'q']).is_some_and(|word| mnemonics().binary_search(&word.to_string()).is_ok())
+}
+
+pub(super) fn is_register(word: &str) -> bool
+{
+    registers().binary_search(&word.trim_start_matches('%').to_lowercase()).is_ok()
+}
+
+pub(super) fn is_word_char(c: char) -> bool
+{
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_token_char(c: char) -> bool
+{
+    is_word_char(c) || matches!(c, '.' | '$' | '%' | '@' | '?')
+}
+
+fn line_start(string: &str, cursor: usize) -> usize
+{
+    string[..cursor].rfind('\n').map(|index| index + 1).unwrap_or(0)
+}
+
+fn line_end(string: &str, cursor: usize) -> usize
+{
+    string[cursor..].find('\n').map(|index| cursor + index).unwrap_or(string.len())
+}
+
+fn indentation(line: &str) -> &str
+{
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+fn previous_word(string: &str, cursor: usize) -> usize
+{
+    let before = &string[..cursor];
+    let end = before.trim_end_matches(|c: char| !is_word_char(c)).len();
+    before[..end].rfind(|c: char| !is_word_char(c)).map(|index| index + 1).unwrap_or(0)
+}
+
+fn next_word(string: &str, cursor: usize) -> usize
+{
+    let after = &string[cursor..];
+    let start = after.len() - after.trim_start_matches(|c: char| !is_word_char(c)).len();
+    after[start..].find(|c: char| !is_word_char(c)).map(|index| cursor + start + index).unwrap_or(string.len())
+}
+
+// the cursor keeps its column when moving to a shorter line and back
+fn move_vertically(string: &str, cursor: usize, up: bool) -> usize
+{
+    let start = line_start(string, cursor);
+    let column = cursor - start;
+    if up
+    {
+        if start == 0
+        {
+            return 0;
+        }
+        let previous_start = line_start(string, start - 1);
+        previous_start + column.min(start - 1 - previous_start)
+    }
+    else
+    {
+        let end = line_end(string, cursor);
+        if end == string.len()
+        {
+            return string.len();
+        }
+        let next_start = end + 1;
+        next_start + column.min(line_end(string, next_start) - next_start)
+    }
+}
+
+// the first word of a line, after an optional label, is the mnemonic
+fn is_first_word(before: &str) -> bool
+{
+    let before = before.trim();
+    before.is_empty() || before.strip_suffix(':').is_some_and(|label| !label.is_empty() && label.chars().all(is_token_char))
+}
+
+fn longest_common_prefix<'b>(words: &[&'b str]) -> &'b str
+{
+    let first = words[0];
+    let len = words.iter().skip(1).fold(first.len(), |len, word|
+    {
+        first.bytes().zip(word.bytes()).take(len).take_while(|(a, b)| a == b).count()
+    });
+    &first[..len]
+}
+
+impl <'a> App<'a>
+{
+    // returns the style of every character of the assembly
+    pub(super) fn get_assembly_styles(color_settings: &ColorSettings, assembly: &str) -> Vec<Style>
+    {
+        let mut styles = Vec::with_capacity(assembly.len());
+        for (i, line) in assembly.split('\n').enumerate()
+        {
+            if i > 0
+            {
+                styles.push(color_settings.menu_text);
+            }
+            let chars: Vec<char> = line.chars().collect();
+            let mut first_word = true;
+            let mut j = 0;
+            while j < chars.len()
+            {
+                let c = chars[j];
+                if c == ';' || c == '#'
+                {
+                    styles.extend(std::iter::repeat_n(color_settings.assembly_comment, chars.len() - j));
+                    break;
+                }
+                if !is_token_char(c)
+                {
+                    styles.push(color_settings.menu_text);
+                    j += 1;
+                    continue;
+                }
+                let start = j;
+                while j < chars.len() && is_token_char(chars[j])
+                {
+                    j += 1;
+                }
+                let word: String = chars[start..j].iter().collect();
+                let is_label = first_word && chars[j..].iter().find(|c| !c.is_whitespace()) == Some(&':');
+                let style = if word.trim_start_matches(['$', '%']).starts_with(|c: char| c.is_ascii_digit())
+                {
+                    color_settings.assembly_immediate
+                }
+                else if is_label
+                {
+                    color_settings.assembly_label
+                }
+                else if first_word && is_mnemonic(&word)
+                {
+                    color_settings.assembly_default
+                }
+                else if is_register(&word)
+                {
+                    color_settings.assembly_register
+                }
+                else
+                {
+                    color_settings.menu_text
+                };
+                // prefixes such as lock are not mnemonics, the next word is highlighted too
+                first_word = is_label || (first_word && !is_mnemonic(&word));
+                styles.extend(std::iter::repeat_n(style, j - start));
+            }
+        }
+        styles
+    }
+
+    // handles the keys that are specific to the patch editor, returns false if the key must be handled as text
+    pub(super) fn handle_assembly_edit(assembly: &mut String, cursor: &mut usize, event: &event::Event, language: Language) -> bool
+    {
+        let event = match event
+        {
+            event::Event::Key(event) if event.kind == event::KeyEventKind::Press => event,
+            _ => return false,
+        };
+        let control = event.modifiers.contains(KeyModifiers::CONTROL);
+        let start = line_start(assembly, *cursor);
+        let end = line_end(assembly, *cursor);
+        match event.code
+        {
+            KeyCode::Left if control =>
+            {
+                *cursor = previous_word(assembly, *cursor);
+            },
+            KeyCode::Right if control =>
+            {
+                *cursor = next_word(assembly, *cursor);
+            },
+            KeyCode::Backspace if control =>
+            {
+                let word = previous_word(assembly, *cursor);
+                assembly.replace_range(word..*cursor, "");
+                *cursor = word;
+            },
+            KeyCode::Delete if control =>
+            {
+                let word = next_word(assembly, *cursor);
+                assembly.replace_range(*cursor..word, "");
+            },
+            KeyCode::Up | KeyCode::Down =>
+            {
+                *cursor = move_vertically(assembly, *cursor, event.code == KeyCode::Up);
+            },
+            KeyCode::Home if control =>
+            {
+                *cursor = 0;
+            },
+            KeyCode::End if control =>
+            {
+                *cursor = assembly.len();
+            },
+            // the first press moves after the indentation, the second to the start of the line
+            KeyCode::Home =>
+            {
+                let indented = start + indentation(&assembly[start..end]).len();
+                *cursor = if *cursor == indented { start } else { indented };
+            },
+            KeyCode::End =>
+            {
+                *cursor = end;
+            },
+            KeyCode::Enter if event.modifiers.contains(KeyModifiers::SHIFT) =>
+            {
+                let new_line = format!("\n{}", indentation(&assembly[start..*cursor]));
+                assembly.insert_str(*cursor, &new_line);
+                *cursor += new_line.len();
+            },
+            KeyCode::Char('k') if control =>
+            {
+                // the cursor moves to the start of the next line, or of the previous one if the last line is removed
+                let (from, to) = if end < assembly.len() { (start, end + 1) } else { (start.saturating_sub(1), end) };
+                assembly.replace_range(from..to, "");
+                *cursor = line_start(assembly, from);
+            },
+            KeyCode::Char('d') if control =>
+            {
+                let line = format!("\n{}", &assembly[start..end]);
+                assembly.insert_str(end, &line);
+                *cursor += line.len();
+            },
+            KeyCode::Tab =>
+            {
+                if language != Language::Assembly || !Self::complete_assembly(assembly, cursor)
+                {
+                    assembly.insert_str(*cursor, INDENT);
+                    *cursor += INDENT.len();
+                }
+            },
+            KeyCode::BackTab =>
+            {
+                let removed = indentation(&assembly[start..end]).len().min(INDENT.len());
+                assembly.replace_range(start..start + removed, "");
+                *cursor = (*cursor).saturating_sub(removed).max(start);
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    // completes the word before the cursor with a mnemonic or a register, returns false if there is no word
+    fn complete_assembly(assembly: &mut String, cursor: &mut usize) -> bool
+    {
+        let start = line_start(assembly, *cursor);
+        let before = &assembly[start..*cursor];
+        let word_start = before.rfind(|c: char| !is_word_char(c)).map(|index| index + 1).unwrap_or(0);
+        let prefix = &before[word_start..];
+        if prefix.is_empty()
+        {
+            return false;
+        }
+        let first_word = is_first_word(&before[..word_start]);
+        let candidates = if first_word { mnemonics() } else { registers() };
+        let lowercase_prefix = prefix.to_lowercase();
+        let matches: Vec<&str> = candidates.iter()
+            .map(|candidate| candidate.as_str())
+            .filter(|candidate| candidate.starts_with(&lowercase_prefix))
+            .collect();
+        if matches.is_empty()
+        {
+            return true;
+        }
+        let mut completion = longest_common_prefix(&matches)[prefix.len()..].to_string();
+        if prefix.chars().any(|c| c.is_ascii_uppercase()) && !prefix.chars().any(|c| c.is_ascii_lowercase())
+        {
+            completion = completion.to_uppercase();
+        }
+        if matches.len() == 1 && first_word
+        {
+            completion.push(' ');
+        }
+        assembly.insert_str(*cursor, &completion);
+        *cursor += completion.len();
+        true
+    }
+
+    pub(super) fn get_patch_popup(&self, assembly: String, cursor: usize, language: Language) -> PopupState
+    {
+        let (preview, diagnostics) = self.bytes_from_source_with_diagnostics(&assembly, language, self.get_patch_virtual_address());
+        PopupState::Patch { assembly, preview, diagnostics, cursor, language }
+    }
+
+    pub(super) fn compile_edited_patch(&mut self)
+    {
+        if self.patch_edit_time.is_none_or(|time| time.elapsed() < PATCH_COMPILE_DELAY)
+        {
+            return;
+        }
+        self.patch_edit_time = None;
+        if let Some(PopupState::Patch { assembly, language, .. }) = &self.popup
+        {
+            let result = self.bytes_from_source_with_diagnostics(assembly, *language, self.get_patch_virtual_address());
+            if let Some(PopupState::Patch { preview, diagnostics, .. }) = &mut self.popup
+            {
+                (*preview, *diagnostics) = result;
+            }
+        }
+    }
+
+    // a loaded snippet is inserted at the cursor so that snippets can be combined
+    pub(super) fn load_snippet(&mut self, path: &str, assembly: &mut String, cursor: &mut usize) -> Result<(), String>
+    {
+        let snippet = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let snippet = snippet.replace("\r\n", "\n");
+        let snippet = snippet.trim_end_matches('\n');
+        assembly.insert_str(*cursor, snippet);
+        *cursor += snippet.len();
+        self.log(NotificationLevel::Info, &format!("Loaded snippet {}", path));
+        Ok(())
+    }
+
+    pub(super) fn save_snippet(&mut self, path: &str, assembly: &str) -> Result<(), String>
+    {
+        std::fs::write(path, format!("{}\n", assembly)).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        self.log(NotificationLevel::Info, &format!("Saved snippet {}", path));
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
+use std::time::Instant;
+
 use crossterm::event::{self, KeyCode, KeyModifiers};
 
-use super::{popup_state::PopupState, App};
+use crate::asm::compiler::Language;
+
+use super::{clipboard::CopyItem, export::ExportRegion, popup_state::PopupState, run_command::Command, selection::SelectionOperation, App};
 
 impl <'a> App<'a>
 {
@@ -12,31 +16,45 @@ impl <'a> App<'a>
                 match event.code
                 {
                     KeyCode::Up => {
+                        self.update_selection_for_modifiers(event.modifiers);
                         self.move_cursor(0, -1);
                     },
                     KeyCode::Down => {
+                        self.update_selection_for_modifiers(event.modifiers);
                         self.move_cursor(0, 1);
                     },
                     KeyCode::Left => {
+                        self.update_selection_for_modifiers(event.modifiers);
                         self.move_cursor(-1, 0);
                     },
                     KeyCode::Right => {
+                        self.update_selection_for_modifiers(event.modifiers);
                         self.move_cursor(1, 0);
                     },
                     KeyCode::PageUp => {
+                        self.update_selection_for_modifiers(event.modifiers);
                         self.move_cursor_page_up();
                     },
                     KeyCode::PageDown => {
+                        self.update_selection_for_modifiers(event.modifiers);
                         self.move_cursor_page_down();
                     },
                     KeyCode::Home => 
                     {
+                        self.update_selection_for_modifiers(event.modifiers);
                         self.move_cursor_to_start();
                     }
                     KeyCode::End => 
                     {
+                        self.update_selection_for_modifiers(event.modifiers);
                         self.move_cursor_to_end();
                     }
+                    KeyCode::Esc => {
+                        self.clear_selection();
+                    },
+                    KeyCode::Tab => {
+                        self.highlighted_register = self.highlighted_register.wrapping_add(1);
+                    },
                     KeyCode::Char(c) if event.modifiers.contains(event::KeyModifiers::CONTROL) => {
                         match c
                         {
@@ -53,6 +71,11 @@ impl <'a> App<'a>
                             's' => {
                                 self.popup = Some(PopupState::Save(false));
                             },
+                            'n' => {
+                                let (_, address) = self.get_cursor_location();
+                                let value = self.get_symbol_at(address);
+                                self.popup = Some(PopupState::EditSymbol { address, cursor: value.len(), value });
+                            },
                             'x' => {
                                 if self.dirty
                                 {
@@ -63,6 +86,10 @@ impl <'a> App<'a>
                                     self.needs_to_exit = true;
                                 }
                             },
+                            'v' => {
+                                let text = self.clipboard.clone().ok_or("The clipboard is empty")?;
+                                self.paste_hex(&text)?;
+                            },
                             _ => {}
                         }
                     },
@@ -80,17 +107,59 @@ impl <'a> App<'a>
                                 self.popup = Some(PopupState::Log(0));
                             },
                             ' ' => {
-                                self.popup = Some(PopupState::Run { command: String::new(), cursor: 0, results: self.find_commands(""), scroll: 0 });
+                                self.popup = Some(PopupState::Run { command: String::new(), cursor: 0, results: self.find_commands(""), scroll: 0, history: 0 });
                             }
                             's' => {
                                 self.popup = Some(PopupState::FindSymbol { filter: String::new(), symbols: Vec::new(), cursor: 0, scroll: 0 });
                             },
                             'p' => {
-                                self.popup = Some(PopupState::Patch { assembly: String::new(), preview: Ok(Vec::new()), cursor: 0});
+                                self.popup = Some(PopupState::Patch { assembly: String::new(), preview: Ok(Vec::new()), diagnostics: Vec::new(), cursor: 0, language: Language::Assembly });
                             },
                             'j' => {
                                 self.popup = Some(PopupState::JumpToAddress { location: String::new(), cursor: 0});
                             },
+                            'i' => {
+                                if let (super::info_mode::InfoMode::Template, Some(field)) = (self.info_mode, self.get_template_field_at_cursor())
+                                {
+                                    let value = self.get_template_edit_value(field);
+                                    self.popup = Some(PopupState::EditTemplateField { field, cursor: value.len(), value });
+                                }
+                                else
+                                {
+                                    let value = self.get_inspector_edit_value(0);
+                                    self.popup = Some(PopupState::EditValue { field: 0, cursor: value.len(), value });
+                                }
+                            },
+                            'n' => {
+                                self.update_strings();
+                                self.popup = Some(PopupState::Strings { filter: String::new(), cursor: 0, strings: self.find_strings(""), scroll: 0 });
+                            },
+                            'y' => {
+                                self.popup = Some(PopupState::Copy(0));
+                            },
+                            'r' => {
+                                let value = self.get_label_at_cursor();
+                                self.popup = Some(PopupState::EditLabel { cursor: value.len(), value });
+                            },
+                            ';' => {
+                                let value = self.get_comment_at_cursor();
+                                self.popup = Some(PopupState::EditComment { cursor: value.len(), value });
+                            },
+                            'm' => {
+                                self.popup = Some(PopupState::AddBookmark { name: String::new(), cursor: 0 });
+                            },
+                            'k' => {
+                                self.popup = Some(PopupState::Bookmarks(0));
+                            },
+                            'u' => {
+                                self.toggle_data_at_cursor()?;
+                            },
+                            'o' => {
+                                self.popup = Some(PopupState::Selection { operation: 0, argument: String::new(), cursor: 0 });
+                            },
+                            't' => {
+                                self.popup = Some(PopupState::LoadTemplate { path: String::new(), cursor: 0 });
+                            },
                             'v' => {
                                 match self.info_mode {
                                     super::info_mode::InfoMode::Text => 
@@ -99,6 +168,16 @@ impl <'a> App<'a>
                                         self.update_hex_cursor();
                                     },
                                     super::info_mode::InfoMode::Assembly => 
+                                    {
+                                        self.info_mode = super::info_mode::InfoMode::Inspector;
+                                        self.update_hex_cursor();
+                                    },
+                                    super::info_mode::InfoMode::Inspector => 
+                                    {
+                                        self.info_mode = super::info_mode::InfoMode::Template;
+                                        self.update_hex_cursor();
+                                    },
+                                    super::info_mode::InfoMode::Template => 
                                     {
                                         self.info_mode = super::info_mode::InfoMode::Text;
                                         self.update_hex_cursor();
@@ -126,9 +205,26 @@ impl <'a> App<'a>
                     event::MouseEventKind::ScrollRight => {
                         self.move_cursor(1, 0);
                     },
+                    event::MouseEventKind::Down(event::MouseButton::Left) => {
+                        if let Some(index) = self.byte_at_screen_position(event.column, event.row)
+                        {
+                            self.clear_selection();
+                            self.jump_to(index, false);
+                        }
+                    },
+                    event::MouseEventKind::Drag(event::MouseButton::Left) => {
+                        if let Some(index) = self.byte_at_screen_position(event.column, event.row)
+                        {
+                            self.start_selection();
+                            self.jump_to(index, false);
+                        }
+                    },
                     _ => {}
                 }
             },
+            event::Event::Paste(text) => {
+                self.paste_hex(&text)?;
+            },
             event::Event::Resize(width, _height) => {
                 self.resize_if_needed(width);
             },
@@ -210,6 +306,16 @@ impl <'a> App<'a>
                     _ => {}
                 }
             },
+            event::Event::Paste(text) => {
+                let text: String = text.chars()
+                    .filter(|c| (multiline && *c == '\n') || !c.is_control())
+                    .filter(|c| charset.is_none() || charset.expect("Just checked").contains(*c))
+                    .map(|c| if capitalize { c.to_ascii_uppercase() } else { c })
+                    .take(max_len.map(|max_len| max_len.saturating_sub(string.len())).unwrap_or(usize::MAX))
+                    .collect();
+                string.insert_str(*cursor, &text);
+                *cursor += text.len();
+            },
             _ => {}
         }
         Ok(())
@@ -244,10 +350,87 @@ impl <'a> App<'a>
 
     fn handle_event_popup(&mut self, event: event::Event) -> Result<(), Box<dyn std::error::Error>>
     {
+        let event = match event
+        {
+            event::Event::Key(key) if key.kind == event::KeyEventKind::Press && 
+                key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('v') =>
+            {
+                event::Event::Paste(self.clipboard.clone().ok_or("The clipboard is empty")?)
+            },
+            event => event
+        };
+        if let (event::Event::Key(key), Some(PopupState::FindSymbol { filter, symbols, scroll, .. })) = (&event, &self.popup)
+        {
+            if key.kind == event::KeyEventKind::Press && key.modifiers.contains(KeyModifiers::CONTROL)
+            {
+                let symbol = self.get_fuzzy_symbol(filter, symbols, *scroll);
+                match (key.code, symbol)
+                {
+                    (KeyCode::Char('n'), _) =>
+                    {
+                        let (_, address) = self.get_cursor_location();
+                        self.popup = Some(PopupState::EditSymbol { address, cursor: filter.len(), value: filter.clone() });
+                    },
+                    (KeyCode::Char('r'), Some((address, name))) =>
+                    {
+                        self.popup = Some(PopupState::EditSymbol { address, cursor: name.len(), value: name });
+                    },
+                    (KeyCode::Char('d'), Some((address, _))) =>
+                    {
+                        self.remove_symbol(address);
+                        self.popup = None;
+                    },
+                    _ => {}
+                }
+                return Ok(());
+            }
+        }
+        if let (event::Event::Key(key), Some(PopupState::Patch { assembly, preview, cursor, language, .. })) = (&event, &self.popup)
+        {
+            if key.kind == event::KeyEventKind::Press && key.modifiers.contains(KeyModifiers::CONTROL)
+            {
+                match (key.code, preview)
+                {
+                    (KeyCode::Char('r'), Ok(preview)) =>
+                    {
+                        let patch_offset = self.get_patch_offset();
+                        let patch_len = preview.len();
+                        self.remove_relocations_in(patch_offset, patch_offset + patch_len);
+                        return Ok(());
+                    },
+                    (KeyCode::Char('o'), _) |
+                    (KeyCode::Char('s'), _) =>
+                    {
+                        self.popup = Some(PopupState::PatchSnippet { 
+                            assembly: assembly.clone(), 
+                            patch_cursor: *cursor, 
+                            language: *language,
+                            path: String::new(), 
+                            cursor: 0, 
+                            save: key.code == KeyCode::Char('s') 
+                        });
+                        return Ok(());
+                    },
+                    (KeyCode::Char('l'), _) =>
+                    {
+                        self.popup = Some(self.get_patch_popup(assembly.clone(), *cursor, language.toggle()));
+                        return Ok(());
+                    },
+                    (KeyCode::Char('e'), _) =>
+                    {
+                        let (assembly, language) = (assembly.clone(), *language);
+                        self.popup = None;
+                        self.patch_in_code_cave(&assembly, language);
+                        return Ok(());
+                    },
+                    _ => {}
+                }
+            }
+        }
         let mut popup = self.popup.clone();
         match &mut popup
         {
-            Some(PopupState::Run {command, cursor, results, scroll: _scroll}) => 
+            Some(PopupState::Run {command, cursor, results, ..}) => 
             {
                 Self::handle_string_edit(command, cursor, &event, None, false, None, false)?;
                 *results = self.find_commands(command);
@@ -257,15 +440,57 @@ impl <'a> App<'a>
                 Self::handle_string_edit(filter, cursor, &event, None, false, None, false)?;
                 *symbols = self.find_symbols(filter);
             }
-            Some(PopupState::Patch {assembly, preview, cursor}) =>
+            Some(PopupState::Strings {filter, strings, cursor, scroll: _scroll}) =>
+            {
+                Self::handle_string_edit(filter, cursor, &event, None, false, None, false)?;
+                *strings = self.find_strings(filter);
+            }
+            Some(PopupState::Patch {assembly, preview, diagnostics, cursor, language}) =>
+            {
+                let old_assembly = assembly.clone();
+                if !Self::handle_assembly_edit(assembly, cursor, &event, *language)
+                {
+                    Self::handle_string_edit(assembly, cursor, &event, None, false, None, true)?;
+                }
+                match language
+                {
+                    Language::Assembly => (*preview, *diagnostics) = self.bytes_from_source_with_diagnostics(assembly, *language, self.get_patch_virtual_address()),
+                    // running the compiler on every key would block the editor, the last preview is kept until the source stops changing
+                    Language::C if *assembly != old_assembly => self.patch_edit_time = Some(Instant::now()),
+                    Language::C => {},
+                }
+            }
+            Some(PopupState::PatchSnippet {path, cursor, ..}) =>
             {
-                Self::handle_string_edit(assembly, cursor, &event, None, false, None, true)?;
-                *preview = self.bytes_from_assembly(&assembly, self.get_current_instruction().virtual_ip());
+                Self::handle_string_edit(path, cursor, &event, None, false, None, false)?;
             }
             Some(PopupState::JumpToAddress {location: address, cursor}) =>
             {
                 Self::handle_string_edit(address, cursor, &event, None, false, None, false)?;
             }
+            Some(PopupState::EditValue {value, cursor, ..}) |
+            Some(PopupState::EditTemplateField {value, cursor, ..}) =>
+            {
+                Self::handle_string_edit(value, cursor, &event, None, false, None, false)?;
+            }
+            Some(PopupState::LoadTemplate {path, cursor}) =>
+            {
+                Self::handle_string_edit(path, cursor, &event, None, false, None, false)?;
+            }
+            Some(PopupState::EditSymbol {value, cursor, ..}) |
+            Some(PopupState::EditLabel {value, cursor}) |
+            Some(PopupState::EditComment {value, cursor}) |
+            Some(PopupState::AddBookmark {name: value, cursor}) |
+            Some(PopupState::AddSection {value, cursor}) =>
+            {
+                Self::handle_string_edit(value, cursor, &event, None, false, None, false)?;
+            }
+            Some(PopupState::SaveAs {path: argument, cursor, ..}) |
+            Some(PopupState::Export {path: argument, cursor, ..}) |
+            Some(PopupState::Selection {argument, cursor, ..}) =>
+            {
+                Self::handle_string_edit(argument, cursor, &event, None, false, None, false)?;
+            }
             _ => {}
         }
 
@@ -296,30 +521,120 @@ impl <'a> App<'a>
                     KeyCode::Enter if !event.modifiers.contains(KeyModifiers::SHIFT) => {
                         match &mut popup
                         {
-                            Some(PopupState::Run { command, cursor: _cursor, results: _results, scroll }) =>
+                            Some(PopupState::Run { command, scroll, .. }) =>
                             {
+                                // commands can open another popup
+                                self.popup = None;
                                 self.run_command(command, *scroll)?;
-                                popup = None;
+                                popup = self.popup.clone();
                             }
                             Some(PopupState::FindSymbol {filter, symbols, cursor: _cursor, scroll}) =>
                             {
                                 self.jump_to_fuzzy_symbol(&filter, &symbols, *scroll);
                                 popup = None;
                             }
+                            Some(PopupState::Strings {strings, scroll, ..}) =>
+                            {
+                                self.jump_to_string(strings, *scroll);
+                                popup = None;
+                            }
                             Some(PopupState::Log(_)) =>
                             {
                                 popup = None;
                             }
-                            Some(PopupState::Patch {assembly, preview: _preview, cursor: _cursor}) =>
+                            Some(PopupState::Patch {assembly, language, ..}) =>
                             {
-                                self.patch(&assembly);
+                                self.patch(&assembly, *language);
                                 popup = None;
                             }
+                            Some(PopupState::PatchSnippet {assembly, patch_cursor, language, path, save, ..}) =>
+                            {
+                                if *save
+                                {
+                                    self.save_snippet(path, assembly)?;
+                                }
+                                else
+                                {
+                                    self.load_snippet(path, assembly, patch_cursor)?;
+                                    *language = Language::from_path(path);
+                                }
+                                popup = Some(self.get_patch_popup(assembly.clone(), *patch_cursor, *language));
+                            }
                             Some(PopupState::JumpToAddress {location, cursor: _cursor}) =>
                             {
                                 self.jump_to_symbol(&location);
                                 popup = None;
                             }
+                            Some(PopupState::EditValue {field, value, cursor: _cursor}) =>
+                            {
+                                self.edit_inspector_field(*field, value)?;
+                                popup = None;
+                            }
+                            Some(PopupState::EditTemplateField {field, value, cursor: _cursor}) =>
+                            {
+                                self.edit_template_field(*field, value)?;
+                                popup = None;
+                            }
+                            Some(PopupState::LoadTemplate {path, cursor: _cursor}) =>
+                            {
+                                self.load_template(path)?;
+                                if self.template.is_some()
+                                {
+                                    self.info_mode = super::info_mode::InfoMode::Template;
+                                    self.update_hex_cursor();
+                                }
+                                popup = None;
+                            }
+                            Some(PopupState::Copy(selected)) =>
+                            {
+                                self.copy_item(CopyItem::all()[*selected]);
+                                popup = None;
+                            }
+                            Some(PopupState::EditSymbol {address, value, cursor: _cursor}) =>
+                            {
+                                self.set_symbol(*address, value)?;
+                                popup = None;
+                            }
+                            Some(PopupState::EditLabel {value, cursor: _cursor}) =>
+                            {
+                                self.set_label_at_cursor(value)?;
+                                popup = None;
+                            }
+                            Some(PopupState::EditComment {value, cursor: _cursor}) =>
+                            {
+                                self.set_comment_at_cursor(value);
+                                popup = None;
+                            }
+                            Some(PopupState::AddBookmark {name, cursor: _cursor}) =>
+                            {
+                                self.add_bookmark_at_cursor(name);
+                                popup = None;
+                            }
+                            Some(PopupState::AddSection {value, cursor: _cursor}) =>
+                            {
+                                self.run_command_with_arguments(Command::AddSection, value)?;
+                                popup = None;
+                            }
+                            Some(PopupState::SaveAs {path, cursor: _cursor, switch}) =>
+                            {
+                                self.save_as(path, *switch)?;
+                                popup = None;
+                            }
+                            Some(PopupState::Export {region, path, cursor: _cursor}) =>
+                            {
+                                self.export(ExportRegion::all()[*region], path)?;
+                                popup = None;
+                            }
+                            Some(PopupState::Bookmarks(scroll)) =>
+                            {
+                                self.jump_to_bookmark(*scroll);
+                                popup = None;
+                            }
+                            Some(PopupState::Selection {operation, argument, cursor: _cursor}) =>
+                            {
+                                self.run_selection_operation(SelectionOperation::all()[*operation], argument)?;
+                                popup = None;
+                            }
                             Some(PopupState::Save(yes_selected)) =>
                             {
                                 if *yes_selected
@@ -360,7 +675,7 @@ impl <'a> App<'a>
                     KeyCode::Down => {
                         match &mut popup
                         {
-                            Some(PopupState::Run { command: _command, cursor: _cursor, results, scroll }) =>
+                            Some(PopupState::Run { results, scroll, .. }) =>
                             {
                                 if results.is_empty()
                                 {
@@ -389,6 +704,10 @@ impl <'a> App<'a>
                                     Self::handle_popup_scroll(scroll, symbols.len(), None, 1);
                                 }
                             },
+                            Some(PopupState::Strings { strings, scroll, .. }) =>
+                            {
+                                Self::handle_popup_scroll(scroll, strings.len(), None, 1);
+                            }
                             Some(PopupState::Log(scroll)) =>
                             {
                                 Self::handle_popup_scroll(scroll, self.log.len(), Some(self.get_scrollable_popup_line_count()?), -1);
@@ -397,20 +716,50 @@ impl <'a> App<'a>
                             {
                                 Self::handle_popup_scroll(scroll, self.help_list.len(), Some(self.get_scrollable_popup_line_count()?), 1);
                             }
+                            Some(PopupState::Selection { operation, .. }) =>
+                            {
+                                *operation = (*operation + 1) % SelectionOperation::all().len();
+                            }
+                            Some(PopupState::Copy(selected)) =>
+                            {
+                                *selected = (*selected + 1) % CopyItem::all().len();
+                            }
+                            Some(PopupState::Export { region, .. }) =>
+                            {
+                                *region = (*region + 1) % ExportRegion::all().len();
+                            }
+                            Some(PopupState::SaveAs { switch, .. }) =>
+                            {
+                                *switch = !*switch;
+                            }
+                            Some(PopupState::Bookmarks(scroll)) =>
+                            {
+                                Self::handle_popup_scroll(scroll, self.project.bookmarks.len(), None, 1);
+                            }
+                            Some(PopupState::EditValue { field, value, cursor }) =>
+                            {
+                                *field = (*field + 1) % self.inspector_fields().len();
+                                *value = self.get_inspector_edit_value(*field);
+                                *cursor = value.len();
+                            }
                             _ => {}
                         }
                     },
                     KeyCode::Up => {
                         match &mut popup
                         {
-                            Some(PopupState::Run { command: _command, cursor: _cursor, results: _results, scroll }) =>
+                            Some(PopupState::Run { results, scroll, .. }) =>
                             {
-                                Self::handle_popup_scroll(scroll, _results.len(), None, -1);
+                                Self::handle_popup_scroll(scroll, results.len(), None, -1);
                             }
                             Some(PopupState::FindSymbol { filter: _filter, symbols, cursor: _cursor, scroll }) =>
                             {
                                 Self::handle_popup_scroll(scroll, symbols.len(), None, -1);
                             },
+                            Some(PopupState::Strings { strings, scroll, .. }) =>
+                            {
+                                Self::handle_popup_scroll(scroll, strings.len(), None, -1);
+                            }
                             Some(PopupState::Log(scroll)) =>
                             {
                                 Self::handle_popup_scroll(scroll, self.log.len(), Some(self.get_scrollable_popup_line_count()?), 1);
@@ -419,18 +768,90 @@ impl <'a> App<'a>
                             {
                                 Self::handle_popup_scroll(scroll, self.help_list.len(), Some(self.get_scrollable_popup_line_count()?), -1);
                             }
+                            Some(PopupState::Bookmarks(scroll)) =>
+                            {
+                                Self::handle_popup_scroll(scroll, self.project.bookmarks.len(), None, -1);
+                            }
+                            Some(PopupState::Copy(selected)) =>
+                            {
+                                let items_count = CopyItem::all().len();
+                                *selected = (*selected + items_count - 1) % items_count;
+                            }
+                            Some(PopupState::Selection { operation, .. }) =>
+                            {
+                                let operations_count = SelectionOperation::all().len();
+                                *operation = (*operation + operations_count - 1) % operations_count;
+                            }
+                            Some(PopupState::Export { region, .. }) =>
+                            {
+                                let regions_count = ExportRegion::all().len();
+                                *region = (*region + regions_count - 1) % regions_count;
+                            }
+                            Some(PopupState::SaveAs { switch, .. }) =>
+                            {
+                                *switch = !*switch;
+                            }
+                            Some(PopupState::EditValue { field, value, cursor }) =>
+                            {
+                                let fields_count = self.inspector_fields().len();
+                                *field = (*field + fields_count - 1) % fields_count;
+                                *value = self.get_inspector_edit_value(*field);
+                                *cursor = value.len();
+                            }
                             _ => {}
                         }
                     },
+                    KeyCode::Tab => {
+                        if let Some(PopupState::Run { command, cursor, results, scroll, .. }) = &mut popup
+                        {
+                            *command = self.complete_command(command, *scroll);
+                            *cursor = command.len();
+                            *results = self.find_commands(command);
+                            *scroll = 0;
+                        }
+                    },
+                    KeyCode::PageUp |
+                    KeyCode::PageDown => {
+                        if let Some(PopupState::Run { command, cursor, results, scroll, history }) = &mut popup
+                        {
+                            let index = if event.code == KeyCode::PageUp { *history + 1 } else { history.saturating_sub(1) };
+                            if let Some(previous) = self.get_command_from_history(index)
+                            {
+                                *history = index;
+                                *command = previous;
+                                *cursor = command.len();
+                                *results = self.find_commands(command);
+                                *scroll = 0;
+                            }
+                        }
+                        if let Some(PopupState::Strings { filter, strings, scroll, .. }) = &mut popup
+                        {
+                            self.strings_min_length = if event.code == KeyCode::PageUp
+                            {
+                                self.strings_min_length + 1
+                            }
+                            else
+                            {
+                                self.strings_min_length.saturating_sub(1).max(1)
+                            };
+                            self.update_strings();
+                            *strings = self.find_strings(filter);
+                            *scroll = 0;
+                        }
+                    },
                     KeyCode::Esc => {
-                        popup = None;
+                        popup = match popup
+                        {
+                            Some(PopupState::PatchSnippet { assembly, patch_cursor, language, .. }) => Some(self.get_patch_popup(assembly, patch_cursor, language)),
+                            _ => None
+                        };
                     },
                     KeyCode::Char(_) | 
                     KeyCode::Backspace | 
                     KeyCode::Delete => {
                         match &mut popup
                         {
-                            Some(PopupState::Run { command: _command, cursor: _cursor, results: _results, scroll }) => 
+                            Some(PopupState::Run { scroll, .. }) => 
                             {
                                 *scroll = 0;
                             }
@@ -438,6 +859,15 @@ impl <'a> App<'a>
                             {
                                 *scroll = 0;
                             }
+                            Some(PopupState::Strings { scroll, .. }) => 
+                            {
+                                *scroll = 0;
+                            }
+                            Some(PopupState::Bookmarks(scroll)) if event.code == KeyCode::Delete =>
+                            {
+                                self.remove_bookmark(*scroll);
+                                *scroll = (*scroll).min(self.project.bookmarks.len().saturating_sub(1));
+                            }
                             Some(PopupState::Log(scroll)) if event.code == KeyCode::Delete =>
                             {
                                 *scroll = 0;
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+
+use ratatui::text::{Line, Span};
+
+use super::{color_settings::ColorSettings, notification::NotificationLevel, App};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportRegion
+{
+    File,
+    Section,
+    Selection,
+}
+
+impl ExportRegion
+{
+    pub fn all() -> [ExportRegion; 3]
+    {
+        [
+            ExportRegion::File,
+            ExportRegion::Section,
+            ExportRegion::Selection,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            ExportRegion::File => "file",
+            ExportRegion::Section => "section",
+            ExportRegion::Selection => "selection",
+        }
+    }
+
+    pub fn description(&self) -> &'static str
+    {
+        match self
+        {
+            ExportRegion::File => "The whole file.",
+            ExportRegion::Section => "The section at the cursor.",
+            ExportRegion::Selection => "The selected bytes.",
+        }
+    }
+
+    pub fn to_line(&self, color_settings: &ColorSettings, selected: bool) -> Line<'static>
+    {
+        let (s0, s1) = if selected {
+            (color_settings.command_selected, color_settings.command_selected)
+        } else {
+            (color_settings.command_name, color_settings.command_description)
+        };
+        Line::from(vec![Span::styled(self.name(), s0), Span::styled(format!(" {}", self.description()), s1)]).left_aligned()
+    }
+}
+
+impl <'a> App<'a>
+{
+    fn parse_target_path(&self, path: &str) -> Result<PathBuf, String>
+    {
+        let path = path.trim();
+        if path.is_empty()
+        {
+            return Err("No path specified".to_string());
+        }
+        let path = PathBuf::from(path);
+        if path.canonicalize().is_ok_and(|path| path == self.path)
+        {
+            return Err("The path is the one of the open file, use save instead".to_string());
+        }
+        Ok(path)
+    }
+
+    // file offsets of the region, the end is exclusive
+    pub(super) fn get_export_range(&self, region: ExportRegion) -> Result<(usize, usize), String>
+    {
+        match region
+        {
+            ExportRegion::File => Ok((0, self.data.len())),
+            ExportRegion::Section =>
+            {
+                let offset = self.get_cursor_position().global_byte_index as u64;
+                self.header.get_sections().into_iter()
+                    .find(|section| offset >= section.address && offset < section.address + section.size)
+                    .map(|section| (section.address as usize, ((section.address + section.size) as usize).min(self.data.len())))
+                    .ok_or("The cursor is not inside a section".to_string())
+            },
+            ExportRegion::Selection =>
+            {
+                let (start, end) = self.get_selection();
+                Ok((start, end + 1))
+            },
+        }
+    }
+
+    pub(super) fn save_as(&mut self, path: &str, switch: bool) -> Result<(), String>
+    {
+        let path = self.parse_target_path(path)?;
+        let is_new_file = !path.exists();
+        self.prepare_data_for_save();
+        Self::write_file_atomically(&path, &self.data).map_err(|e| e.to_string())?;
+        if is_new_file
+        {
+            // a copy of an executable should stay executable
+            if let Ok(metadata) = std::fs::metadata(&self.path)
+            {
+                std::fs::set_permissions(&path, metadata.permissions()).map_err(|e| e.to_string())?;
+            }
+        }
+        if switch
+        {
+            self.path = path.canonicalize().map_err(|e| e.to_string())?;
+            self.file_state = Self::get_file_state(&self.path);
+            self.backup_created = true;
+            self.dirty = false;
+            self.save_project();
+            self.log(NotificationLevel::Info, &format!("Saved to {}, now editing it", self.path.to_string_lossy()));
+        }
+        else
+        {
+            self.log(NotificationLevel::Info, &format!("Saved a copy to {}", path.to_string_lossy()));
+        }
+        Ok(())
+    }
+
+    pub(super) fn export(&mut self, region: ExportRegion, path: &str) -> Result<(), String>
+    {
+        let path = self.parse_target_path(path)?;
+        let (start, end) = self.get_export_range(region)?;
+        Self::write_file_atomically(&path, &self.data[start..end]).map_err(|e| e.to_string())?;
+        self.log(NotificationLevel::Info, &format!("Exported {} bytes from {:#X} to {}", end - start, start, path.to_string_lossy()));
+        Ok(())
+    }
+}
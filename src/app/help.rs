@@ -35,15 +35,30 @@ impl <'a> App<'a>
         vec![
             HelpLine::new("←→↑↓", "Move and scroll"),
             HelpLine::new("PgUp/PgDn", "Scroll page up/down"),
+            HelpLine::new("Shift+Move", "Select bytes"),
+            HelpLine::new("Esc", "Clear selection"),
             HelpLine::new("Home/End", "Scroll to start/end"),
             HelpLine::new("Space", "Run command"),
             HelpLine::new("^S", "Save"),
             HelpLine::new("^X", "Save and quit"),
             HelpLine::new("^C", "Quit"),
+            HelpLine::new("^N", "Edit symbol at cursor"),
             HelpLine::new("V", "Change view"),
+            HelpLine::new("Tab", "Highlight next register"),
             HelpLine::new("J", "Jumpt to location"),
             HelpLine::new("S", "Search symbol"),
+            HelpLine::new("N", "Search strings"),
             HelpLine::new("P", "Patch assembly"),
+            HelpLine::new("I", "Edit value at cursor"),
+            HelpLine::new("T", "Load template"),
+            HelpLine::new("O", "Selection operations"),
+            HelpLine::new("R", "Label address"),
+            HelpLine::new(";", "Comment instruction"),
+            HelpLine::new("U", "Define as data/code"),
+            HelpLine::new("M", "Add bookmark"),
+            HelpLine::new("K", "List bookmarks"),
+            HelpLine::new("Y", "Copy"),
+            HelpLine::new("^V", "Paste"),
             HelpLine::new("L", "Open log"),
             HelpLine::new("H", "Help"),
         ]
@@ -1,3 +1,5 @@
+use std::{ffi::OsString, io::Write, path::Path, time::SystemTime};
+
 use ratatui::text::{Line, Span, Text};
 use ratatui::style::{Style,Color};
 
@@ -86,6 +88,9 @@ impl <'a> App<'a>
         self.text_cursor = (0, 0);
         self.text_last_byte_index = 0;
         self.address_last_row = 0;
+        self.selection_highlight = None;
+        self.color_template_fields();
+        self.color_relocations();
 
         self.jump_to(old_cursor.global_byte_index, false);
     }
@@ -139,7 +144,7 @@ impl <'a> App<'a>
                 self.dirty = true;
             }
 
-            let style = Self::get_style_for_byte(&self.color_settings, byte);
+            let style = self.get_style_for_byte_at(cursor_position.global_byte_index);
             let [high_char, low_char] = Self::u8_to_hex(byte);
 
             self.hex_view.lines[cursor_position.line_index]
@@ -153,20 +158,67 @@ impl <'a> App<'a>
             self.text_view.lines[cursor_position.line_index]
                 .spans[cursor_position.line_byte_index * 2] = Span::styled(new_str, style);
         }
-        self.edit_assembly(1);
+        self.edit_assembly(self.get_cursor_position().global_byte_index, 1);
         self.update_hex_cursor();
         self.update_text_cursor();
     }
 
+    pub(super) fn write_bytes(&mut self, offset: usize, bytes: &[u8])
+    {
+        let current_instruction = self.get_current_instruction().clone();
+        let bytes = &bytes[..bytes.len().min(self.data.len().saturating_sub(offset))];
+        for (i, byte) in bytes.iter().enumerate()
+        {
+            self.data[offset + i] = *byte;
+        }
+        self.color_instruction_bytes(&current_instruction, true);
+        for (i, byte) in bytes.iter().enumerate()
+        {
+            let style = self.get_style_for_byte_at(offset + i);
+            let cursor_position = self.get_expected_cursor_position(offset + i, true);
+            let [high_byte, low_byte] = Self::u8_to_hex(*byte);
+
+            self.hex_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 3].content = high_byte.to_string().into();
+            self.hex_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 3].style = style;
+            self.hex_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 3 + 1].content = low_byte.to_string().into();
+            self.hex_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 3 + 1].style = style;
+            
+            self.text_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 2].content = Self::u8_to_char(*byte).to_string().into();
+            self.text_view.lines[cursor_position.line_index].spans[cursor_position.line_byte_index * 2].style = style;
+        }
+        self.dirty = true;
+        self.edit_assembly(offset, bytes.len());
+        self.update_cursors();
+    }
+
+    pub(super) fn restyle_bytes(&mut self, from: usize, to: usize)
+    {
+        self.restyle_range(from, to);
+        self.update_cursors();
+    }
+
+    pub(super) fn restyle_range(&mut self, from: usize, to: usize)
+    {
+        for i in from..to.min(self.data.len())
+        {
+            let style = self.get_style_for_byte_at(i);
+            let position = self.get_expected_cursor_position(i, true);
+            for span in self.hex_view.lines[position.line_index].spans.iter_mut().skip(position.line_byte_index * 3).take(3)
+            {
+                span.style = style;
+            }
+            self.text_view.lines[position.line_index].spans[position.line_byte_index * 2].style = style;
+        }
+    }
+
     pub(super) fn update_hex_cursor(&mut self)
     {
         let cursor_position = self.get_cursor_position();
         let instruction = self.get_current_instruction().clone();
         if self.hex_last_byte_index < self.data.len()
         {
-            let old_byte = self.data[self.hex_last_byte_index];
             let old_instruction = self.get_instruction_at(self.hex_last_byte_index).clone();
-            let style = Self::get_style_for_byte(&self.color_settings, old_byte);
+            let style = self.get_style_for_byte_at(self.hex_last_byte_index);
             self.hex_view.lines[self.hex_cursor.0].spans[self.hex_cursor.1].style = style;
             self.color_instruction_bytes(&old_instruction, true);
         }
@@ -187,13 +239,15 @@ impl <'a> App<'a>
 
     pub(super) fn color_instruction_bytes(&mut self, instruction: &AssemblyLine, original_color: bool)
     {
-        if let AssemblyLine::Instruction(instruction) = instruction
+        if let AssemblyLine::Instruction(_) | AssemblyLine::Data(_) = instruction
         {
-            for i in instruction.file_address as usize..instruction.file_address as usize + instruction.instruction.len() {
+            let start = instruction.ip() as usize;
+            let end = start + instruction.size();
+            for i in start..end {
                 let gui_pos = self.get_expected_cursor_position(i, true);
                 let style = if original_color
                 {
-                    Self::get_style_for_byte(&self.color_settings, self.data[i])
+                    self.get_style_for_byte_at(i)
                 }
                 else 
                 {
@@ -201,16 +255,97 @@ impl <'a> App<'a>
                 };
                 self.hex_view.lines[gui_pos.line_index].spans[gui_pos.line_byte_index*3].style = style;
                 self.hex_view.lines[gui_pos.line_index].spans[gui_pos.line_byte_index*3+1].style = style;
-                if i != instruction.file_address as usize + instruction.instruction.len()-1 {
+                if i != end - 1 {
                     self.hex_view.lines[gui_pos.line_index].spans[gui_pos.line_byte_index*3+2].style = style;
                 }
             }
         }
     }
 
+    pub(super) fn apply_save_fixups(&mut self)
+    {
+        let (fixups, warnings) = self.header.save_fixups(&self.data);
+        for fixup in fixups
+        {
+            self.write_bytes(fixup.offset as usize, &fixup.bytes);
+            self.log(NotificationLevel::Info, &fixup.description);
+        }
+        for warning in warnings
+        {
+            self.log(NotificationLevel::Warning, &warning);
+        }
+    }
+
+    pub(super) fn prepare_data_for_save(&mut self)
+    {
+        if self.write_symbols
+        {
+            self.write_symbols_to_data();
+        }
+        self.apply_save_fixups();
+    }
+
+    pub(super) fn get_file_state(path: &Path) -> Option<(SystemTime, u64)>
+    {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
+    // the data is written next to the file and renamed over it, so a failed write never leaves a truncated file
+    pub(super) fn write_file_atomically(path: &Path, data: &[u8]) -> Result<(), std::io::Error>
+    {
+        let mut temporary_name = OsString::from(".");
+        temporary_name.push(path.file_name().unwrap_or_default());
+        temporary_name.push(".hexpatch.tmp");
+        let temporary_path = path.with_file_name(temporary_name);
+        let result = (|| {
+            let mut file = std::fs::File::create(&temporary_path)?;
+            file.write_all(data)?;
+            file.sync_all()?;
+            if let Ok(metadata) = std::fs::metadata(path)
+            {
+                std::fs::set_permissions(&temporary_path, metadata.permissions())?;
+            }
+            std::fs::rename(&temporary_path, path)
+        })();
+        if result.is_err()
+        {
+            let _ = std::fs::remove_file(&temporary_path);
+        }
+        result
+    }
+
     pub(super) fn save_data(&mut self) -> Result<(), std::io::Error>
     {
-        std::fs::write(&self.path, &self.data)?;
+        self.save_data_checked(false)
+    }
+
+    pub(super) fn save_data_checked(&mut self, force: bool) -> Result<(), std::io::Error>
+    {
+        if !force && Self::get_file_state(&self.path) != self.file_state
+        {
+            return Err(std::io::Error::other(format!("{} changed on disk since it was opened, run fsave to overwrite it", self.path.to_string_lossy())));
+        }
+        if self.backup && !self.backup_created && self.path.exists()
+        {
+            let mut backup_name = self.path.file_name().unwrap_or_default().to_os_string();
+            backup_name.push(".bak");
+            let backup_path = self.path.with_file_name(backup_name);
+            // a backup left by an earlier session holds the true original and is never overwritten
+            if backup_path.exists()
+            {
+                self.log(NotificationLevel::Info, &format!("Keeping the existing backup {}", backup_path.to_string_lossy()));
+            }
+            else
+            {
+                std::fs::copy(&self.path, &backup_path)?;
+                self.log(NotificationLevel::Info, &format!("Saved a backup to {}", backup_path.to_string_lossy()));
+            }
+            self.backup_created = true;
+        }
+        self.prepare_data_for_save();
+        Self::write_file_atomically(&self.path, &self.data)?;
+        self.file_state = Self::get_file_state(&self.path);
         self.dirty = false;
         self.log(NotificationLevel::Info, &format!("Saved to {}", self.path.to_string_lossy()));
         Ok(())
@@ -3,5 +3,7 @@
 pub enum InfoMode
 {
     Text,
-    Assembly
+    Assembly,
+    Inspector,
+    Template
 }
@@ -0,0 +1,602 @@
+use ratatui::text::{Line, Span, Text};
+
+use crate::headers::{elf::Endianness, header::Header};
+
+use super::{color_settings::ColorSettings, notification::NotificationLevel, App};
+
+const SECONDS_FROM_1601_TO_1970: i64 = 11_644_473_600;
+const FILETIME_TICKS_PER_SECOND: i64 = 10_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InspectorType
+{
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    Pointer,
+    UnixTime32,
+    UnixTime64,
+    FileTime,
+    Guid,
+    Uleb128,
+    Sleb128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InspectorField
+{
+    pub data_type: InspectorType,
+    pub endianness: Endianness,
+}
+
+impl InspectorField
+{
+    pub fn new(data_type: InspectorType, endianness: Endianness) -> Self
+    {
+        Self { data_type, endianness }
+    }
+
+    pub fn fields(native_endianness: Endianness) -> Vec<InspectorField>
+    {
+        let other_endianness = match native_endianness
+        {
+            Endianness::Little => Endianness::Big,
+            Endianness::Big => Endianness::Little,
+        };
+        let mut fields = vec![
+            InspectorField::new(InspectorType::I8, native_endianness),
+            InspectorField::new(InspectorType::U8, native_endianness),
+        ];
+        for data_type in [
+            InspectorType::I16, InspectorType::U16,
+            InspectorType::I32, InspectorType::U32,
+            InspectorType::I64, InspectorType::U64,
+            InspectorType::F32, InspectorType::F64]
+        {
+            fields.push(InspectorField::new(data_type, native_endianness));
+            fields.push(InspectorField::new(data_type, other_endianness));
+        }
+        fields.extend([
+            InspectorField::new(InspectorType::Pointer, native_endianness),
+            InspectorField::new(InspectorType::UnixTime32, native_endianness),
+            InspectorField::new(InspectorType::UnixTime64, native_endianness),
+            // FILETIME and GUID are Windows structures, they are always little endian
+            InspectorField::new(InspectorType::FileTime, Endianness::Little),
+            InspectorField::new(InspectorType::Guid, Endianness::Little),
+            InspectorField::new(InspectorType::Uleb128, native_endianness),
+            InspectorField::new(InspectorType::Sleb128, native_endianness),
+        ]);
+        fields
+    }
+
+    pub fn name(&self) -> String
+    {
+        let type_name = match self.data_type
+        {
+            InspectorType::I8 => "i8",
+            InspectorType::U8 => "u8",
+            InspectorType::I16 => "i16",
+            InspectorType::U16 => "u16",
+            InspectorType::I32 => "i32",
+            InspectorType::U32 => "u32",
+            InspectorType::I64 => "i64",
+            InspectorType::U64 => "u64",
+            InspectorType::F32 => "f32",
+            InspectorType::F64 => "f64",
+            InspectorType::Pointer => "ptr",
+            InspectorType::UnixTime32 => "time32",
+            InspectorType::UnixTime64 => "time64",
+            InspectorType::FileTime => "FILETIME",
+            InspectorType::Guid => "GUID",
+            InspectorType::Uleb128 => "uleb128",
+            InspectorType::Sleb128 => "sleb128",
+        };
+        match self.data_type
+        {
+            InspectorType::I8 | InspectorType::U8 |
+            InspectorType::FileTime | InspectorType::Guid |
+            InspectorType::Uleb128 | InspectorType::Sleb128 => type_name.to_string(),
+            _ => format!("{} {}", type_name, match self.endianness
+            {
+                Endianness::Little => "LE",
+                Endianness::Big => "BE",
+            })
+        }
+    }
+
+    // None if the size depends on the data
+    pub fn size(&self, bitness: u32) -> Option<usize>
+    {
+        match self.data_type
+        {
+            InspectorType::I8 | InspectorType::U8 => Some(1),
+            InspectorType::I16 | InspectorType::U16 => Some(2),
+            InspectorType::I32 | InspectorType::U32 | InspectorType::F32 | InspectorType::UnixTime32 => Some(4),
+            InspectorType::I64 | InspectorType::U64 | InspectorType::F64 | InspectorType::UnixTime64 | InspectorType::FileTime => Some(8),
+            InspectorType::Pointer => Some(bitness as usize / 8),
+            InspectorType::Guid => Some(16),
+            InspectorType::Uleb128 | InspectorType::Sleb128 => None,
+        }
+    }
+
+    fn read_unsigned(bytes: &[u8], endianness: Endianness) -> u64
+    {
+        let mut value = 0u64;
+        match endianness
+        {
+            Endianness::Little =>
+            {
+                for byte in bytes.iter().rev()
+                {
+                    value = (value << 8) | *byte as u64;
+                }
+            },
+            Endianness::Big =>
+            {
+                for byte in bytes.iter()
+                {
+                    value = (value << 8) | *byte as u64;
+                }
+            },
+        }
+        value
+    }
+
+    fn write_unsigned(value: u64, size: usize, endianness: Endianness) -> Vec<u8>
+    {
+        let bytes = value.to_le_bytes()[..size].to_vec();
+        match endianness
+        {
+            Endianness::Little => bytes,
+            Endianness::Big => bytes.into_iter().rev().collect(),
+        }
+    }
+
+    fn sign_extend(value: u64, size: usize) -> i64
+    {
+        let shift = 64 - size * 8;
+        ((value << shift) as i64) >> shift
+    }
+
+    fn read_leb128(bytes: &[u8], signed: bool) -> Option<(i128, usize)>
+    {
+        let mut value: i128 = 0;
+        let mut shift = 0;
+        for (i, byte) in bytes.iter().take(10).enumerate()
+        {
+            value |= ((byte & 0x7F) as i128) << shift;
+            shift += 7;
+            if byte & 0x80 == 0
+            {
+                if signed && byte & 0x40 != 0
+                {
+                    value -= 1 << shift;
+                }
+                return Some((value, i + 1));
+            }
+        }
+        None
+    }
+
+    fn write_leb128(mut value: i128, signed: bool) -> Vec<u8>
+    {
+        let mut bytes = Vec::new();
+        loop
+        {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            let done = if signed
+            {
+                (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0)
+            }
+            else
+            {
+                value == 0
+            };
+            if done
+            {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    pub fn decode_integer(&self, bytes: &[u8]) -> Option<i128>
+    {
+        let size = match self.data_type
+        {
+            InspectorType::I8 | InspectorType::U8 |
+            InspectorType::I16 | InspectorType::U16 |
+            InspectorType::I32 | InspectorType::U32 |
+            InspectorType::I64 | InspectorType::U64 => self.size(64).expect("Integers have a fixed size"),
+            _ => return None,
+        };
+        if bytes.len() < size
+        {
+            return None;
+        }
+        let raw = Self::read_unsigned(&bytes[..size], self.endianness);
+        match self.data_type
+        {
+            InspectorType::I8 | InspectorType::I16 | InspectorType::I32 | InspectorType::I64 => Some(Self::sign_extend(raw, size) as i128),
+            _ => Some(raw as i128),
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8], header: &Header) -> Option<String>
+    {
+        if let Some(size) = self.size(header.bitness())
+        {
+            if bytes.len() < size
+            {
+                return None;
+            }
+            let raw = Self::read_unsigned(&bytes[..size.min(8)], self.endianness);
+            let value = match self.data_type
+            {
+                InspectorType::U8 | InspectorType::U16 | InspectorType::U32 | InspectorType::U64 =>
+                    format!("{} ({:#X})", raw, raw),
+                InspectorType::I8 | InspectorType::I16 | InspectorType::I32 | InspectorType::I64 =>
+                    format!("{}", Self::sign_extend(raw, size)),
+                InspectorType::F32 => format!("{}", f32::from_bits(raw as u32)),
+                InspectorType::F64 => format!("{}", f64::from_bits(raw)),
+                InspectorType::Pointer =>
+                {
+                    if let Some(symbol) = resolve_address(header, raw)
+                    {
+                        format!("{:#X} <{}>", raw, symbol)
+                    }
+                    else
+                    {
+                        format!("{:#X}", raw)
+                    }
+                },
+                InspectorType::UnixTime32 => format_timestamp(raw as u32 as i64),
+                InspectorType::UnixTime64 => format_timestamp(raw as i64),
+                InspectorType::FileTime =>
+                    format_timestamp((raw / FILETIME_TICKS_PER_SECOND as u64) as i64 - SECONDS_FROM_1601_TO_1970),
+                InspectorType::Guid =>
+                {
+                    let b = &bytes[..16];
+                    format!("{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+                        u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                        u16::from_le_bytes([b[4], b[5]]),
+                        u16::from_le_bytes([b[6], b[7]]),
+                        b[8], b[9], b[10], b[11], b[12], b[13], b[14], b[15])
+                },
+                InspectorType::Uleb128 | InspectorType::Sleb128 => unreachable!("LEB128 has no fixed size"),
+            };
+            Some(value)
+        }
+        else
+        {
+            let signed = self.data_type == InspectorType::Sleb128;
+            Self::read_leb128(bytes, signed).map(|(value, len)| format!("{} ({}B)", value, len))
+        }
+    }
+
+    pub fn encode(&self, value: &str, header: &Header) -> Result<Vec<u8>, String>
+    {
+        let value = value.trim();
+        let size = self.size(header.bitness());
+        match self.data_type
+        {
+            InspectorType::U8 | InspectorType::U16 | InspectorType::U32 | InspectorType::U64 =>
+            {
+                let size = size.expect("Integers have a fixed size");
+                let number = parse_integer(value)?;
+                let max = if size == 8 { u64::MAX as i128 } else { (1i128 << (size * 8)) - 1 };
+                if number < 0 || number > max
+                {
+                    return Err(format!("Value out of range for {}", self.name()));
+                }
+                Ok(Self::write_unsigned(number as u64, size, self.endianness))
+            },
+            InspectorType::I8 | InspectorType::I16 | InspectorType::I32 | InspectorType::I64 =>
+            {
+                let size = size.expect("Integers have a fixed size");
+                let number = parse_integer(value)?;
+                let bits = size * 8 - 1;
+                let (min, max) = (-(1i128 << bits), (1i128 << bits) - 1);
+                if number < min || number > max
+                {
+                    return Err(format!("Value out of range for {}", self.name()));
+                }
+                Ok(Self::write_unsigned(number as i64 as u64, size, self.endianness))
+            },
+            InspectorType::F32 =>
+            {
+                let number = value.parse::<f32>().map_err(|e| e.to_string())?;
+                Ok(Self::write_unsigned(number.to_bits() as u64, 4, self.endianness))
+            },
+            InspectorType::F64 =>
+            {
+                let number = value.parse::<f64>().map_err(|e| e.to_string())?;
+                Ok(Self::write_unsigned(number.to_bits(), 8, self.endianness))
+            },
+            InspectorType::Pointer =>
+            {
+                let size = size.expect("Pointers have a fixed size");
+                let address = if let Some(address) = header.symbol_to_address(value)
+                {
+                    address as i128
+                }
+                else
+                {
+                    parse_integer(value)?
+                };
+                if address < 0 || (size < 8 && address >= 1i128 << (size * 8)) || address > u64::MAX as i128
+                {
+                    return Err(format!("Address out of range for {}", self.name()));
+                }
+                Ok(Self::write_unsigned(address as u64, size, self.endianness))
+            },
+            InspectorType::UnixTime32 =>
+            {
+                let seconds = parse_seconds(value)?;
+                if !(0..=u32::MAX as i64).contains(&seconds)
+                {
+                    return Err(format!("Timestamp out of range for {}", self.name()));
+                }
+                Ok(Self::write_unsigned(seconds as u64, 4, self.endianness))
+            },
+            InspectorType::UnixTime64 =>
+            {
+                let seconds = parse_seconds(value)?;
+                Ok(Self::write_unsigned(seconds as u64, 8, self.endianness))
+            },
+            InspectorType::FileTime =>
+            {
+                let ticks = if let Some(seconds) = parse_timestamp(value)?
+                {
+                    (seconds as i128 + SECONDS_FROM_1601_TO_1970 as i128) * FILETIME_TICKS_PER_SECOND as i128
+                }
+                else
+                {
+                    parse_integer(value).map_err(|_| format!("Invalid timestamp: {}", value))?
+                };
+                if ticks < 0 || ticks > u64::MAX as i128
+                {
+                    return Err(format!("Timestamp out of range for {}", self.name()));
+                }
+                Ok(Self::write_unsigned(ticks as u64, 8, self.endianness))
+            },
+            InspectorType::Guid => parse_guid(value),
+            InspectorType::Uleb128 =>
+            {
+                let number = parse_integer(value)?;
+                if number < 0
+                {
+                    return Err("ULEB128 values can't be negative".to_string());
+                }
+                Ok(Self::write_leb128(number, false))
+            },
+            InspectorType::Sleb128 => Ok(Self::write_leb128(parse_integer(value)?, true)),
+        }
+    }
+}
+
+fn parse_integer(value: &str) -> Result<i128, String>
+{
+    let (negative, digits) = match value.strip_prefix('-')
+    {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let number = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X"))
+    {
+        i128::from_str_radix(hex, 16)
+    }
+    else
+    {
+        digits.parse::<i128>()
+    }.map_err(|_| format!("Invalid number: {}", value))?;
+    Ok(if negative { -number } else { number })
+}
+
+// a date or a number of seconds
+fn parse_seconds(value: &str) -> Result<i64, String>
+{
+    match parse_timestamp(value)?
+    {
+        Some(seconds) => Ok(seconds),
+        None =>
+        {
+            let seconds = parse_integer(value).map_err(|_| format!("Invalid timestamp: {}", value))?;
+            i64::try_from(seconds).map_err(|_| format!("Timestamp out of range: {}", value))
+        }
+    }
+}
+
+fn parse_guid(value: &str) -> Result<Vec<u8>, String>
+{
+    let digits: String = value.chars().filter(|c| !matches!(c, '{' | '}' | '-')).collect();
+    if digits.len() != 32
+    {
+        return Err(format!("Invalid GUID: {}", value));
+    }
+    let mut bytes = Vec::with_capacity(16);
+    for i in 0..16
+    {
+        bytes.push(u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).map_err(|_| format!("Invalid GUID: {}", value))?);
+    }
+    // the first three groups are stored as little endian integers
+    bytes[0..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+    Ok(bytes)
+}
+
+fn days_to_civil(days: i64) -> (i64, u32, u32)
+{
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// the year comes from the user, None if the days overflow
+fn civil_to_days(year: i64, month: u32, day: u32) -> Option<i64>
+{
+    let year = if month <= 2 { year.checked_sub(1)? } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era.checked_mul(146_097)?.checked_add(doe - 719_468)
+}
+
+pub fn format_timestamp(seconds: i64) -> String
+{
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+    let (year, month, day) = days_to_civil(days);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+// accepts "YYYY-MM-DD hh:mm:ss", the time and the "UTC" suffix are optional, None if the value is not a date
+pub fn parse_timestamp(value: &str) -> Result<Option<i64>, String>
+{
+    let parse = || -> Option<(i64, u32, u32, i64, i64, i64)>
+    {
+        let value = value.trim_end_matches("UTC").trim();
+        let mut parts = value.split_whitespace();
+        let date = parts.next()?;
+        let time = parts.next().unwrap_or("00:00:00");
+        let date: Vec<&str> = date.split('-').collect();
+        let time: Vec<&str> = time.split(':').collect();
+        if date.len() != 3 || time.len() != 3
+        {
+            return None;
+        }
+        let year = date[0].parse::<i64>().ok()?;
+        let month = date[1].parse::<u32>().ok()?;
+        let day = date[2].parse::<u32>().ok()?;
+        let hours = time[0].parse::<i64>().ok()?;
+        let minutes = time[1].parse::<i64>().ok()?;
+        let seconds = time[2].parse::<i64>().ok()?;
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || !(0..=23).contains(&hours) || !(0..=59).contains(&minutes) || !(0..=59).contains(&seconds)
+        {
+            return None;
+        }
+        Some((year, month, day, hours, minutes, seconds))
+    };
+    let (year, month, day, hours, minutes, seconds) = match parse()
+    {
+        Some(parts) => parts,
+        None => return Ok(None),
+    };
+    civil_to_days(year, month, day)
+        .and_then(|days| days.checked_mul(86_400))
+        .and_then(|days| days.checked_add(hours * 3600 + minutes * 60 + seconds))
+        .map(Some)
+        .ok_or(format!("Timestamp out of range: {}", value))
+}
+
+fn resolve_address(header: &Header, address: u64) -> Option<String>
+{
+    if let Some(symbols) = header.get_symbols()
+    {
+        if let Some(symbol) = symbols.get(&address)
+        {
+            return Some(symbol.clone());
+        }
+    }
+    let section = header.get_sections().into_iter()
+        .find(|s| address >= s.virtual_address && address < s.virtual_address + s.size)?;
+    if let Some(symbols) = header.get_symbols()
+    {
+        let closest = symbols.iter()
+            .filter(|(symbol_address, _)| **symbol_address >= section.virtual_address && **symbol_address <= address)
+            .max_by_key(|(symbol_address, _)| **symbol_address);
+        if let Some((symbol_address, name)) = closest
+        {
+            return Some(format!("{}+{:#X}", name, address - symbol_address));
+        }
+    }
+    Some(format!("{}+{:#X}", section.name, address - section.virtual_address))
+}
+
+impl <'a> App<'a>
+{
+    pub(super) fn inspector_fields(&self) -> Vec<InspectorField>
+    {
+        InspectorField::fields(self.header.endianness())
+    }
+
+    pub(super) fn inspect_field(&self, field: &InspectorField) -> Option<String>
+    {
+        let offset = self.get_cursor_position().global_byte_index.min(self.data.len());
+        field.decode(&self.data[offset..], &self.header)
+    }
+
+    fn inspector_field_to_line(color_settings: &ColorSettings, field: &InspectorField, value: Option<String>) -> Line<'a>
+    {
+        let mut line = Line::default();
+        line.spans.push(Span::styled(format!("{:>10} ", field.name()), color_settings.inspector_type));
+        match value
+        {
+            Some(value) => line.spans.push(Span::styled(value, color_settings.inspector_value)),
+            None => line.spans.push(Span::styled("-", color_settings.placeholder)),
+        }
+        line
+    }
+
+    pub(super) fn inspector_view(&self) -> Text<'a>
+    {
+        let mut text = Text::default();
+        let offset = self.get_cursor_position().global_byte_index;
+        let mut location = Line::default();
+        location.spans.push(Span::styled(format!("{:>10} ", "offset"), self.color_settings.inspector_type));
+        location.spans.push(Span::styled(format!("{:#X}", offset), self.color_settings.inspector_value));
+        text.lines.push(location);
+        for field in self.inspector_fields()
+        {
+            text.lines.push(Self::inspector_field_to_line(&self.color_settings, &field, self.inspect_field(&field)));
+        }
+        text
+    }
+
+    pub(super) fn get_inspector_edit_value(&self, field_index: usize) -> String
+    {
+        let fields = self.inspector_fields();
+        let value = self.inspect_field(&fields[field_index]).unwrap_or_default();
+        // only keep the part of the value that can be parsed back
+        match fields[field_index].data_type
+        {
+            InspectorType::U8 | InspectorType::U16 | InspectorType::U32 | InspectorType::U64 |
+            InspectorType::Pointer | InspectorType::Uleb128 | InspectorType::Sleb128 =>
+                value.split_whitespace().next().unwrap_or_default().to_string(),
+            _ => value,
+        }
+    }
+
+    pub(super) fn edit_inspector_field(&mut self, field_index: usize, value: &str) -> Result<(), String>
+    {
+        let field = self.inspector_fields()[field_index];
+        let bytes = field.encode(value, &self.header)?;
+        let offset = self.get_cursor_position().global_byte_index;
+        if offset + bytes.len() > self.data.len()
+        {
+            return Err(format!("Not enough bytes to write a {} at {:#X}", field.name(), offset));
+        }
+        self.write_bytes(offset, &bytes);
+        self.log(NotificationLevel::Debug, &format!("Wrote {} {} at {:#X}", field.name(), value, offset));
+        Ok(())
+    }
+}
@@ -14,5 +14,20 @@ pub mod info_mode;
 pub mod run_command;
 pub mod cursor_position;
 pub mod color_settings;
+pub mod inspector;
+pub mod template;
+pub mod strings;
+pub mod selection;
+pub mod clipboard;
+pub mod project;
+pub mod symbols;
+pub mod sections;
+pub mod export;
+pub mod script;
+pub mod signature;
+pub mod relocations;
+pub mod editor;
+pub mod data_ranges;
+pub mod disassembly;
 
-pub use app::App;
\ No newline at end of file
+pub use app::App;
@@ -1,8 +1,15 @@
 use std::error::Error;
 
-use ratatui::{layout::Rect, text::{Line, Span, Text}, Frame};
+use iced_x86::Instruction;
 
-use super::{assembly::AssemblyLine, color_settings::ColorSettings, run_command::Command, App};
+use crate::asm::{assembler::{Diagnostic, DiagnosticLevel}, compiler::Language};
+
+use ratatui::{layout::Rect, style::Style, text::{Line, Span, Text}, Frame};
+
+use super::{assembly::AssemblyLine, color_settings::ColorSettings, clipboard::CopyItem, export::ExportRegion, run_command::Command, selection::SelectionOperation, App};
+
+const PATCH_LISTING_LINES: usize = 4;
+const PATCH_DIAGNOSTIC_LINES: usize = 4;
 
 #[derive(Clone, Debug)]
 pub enum PopupState
@@ -12,7 +19,8 @@ pub enum PopupState
         command: String,
         cursor: usize,
         results: Vec<Command>,
-        scroll: usize
+        scroll: usize,
+        history: usize
     },
     FindSymbol
     {
@@ -21,18 +29,99 @@ pub enum PopupState
         symbols: Vec<(u64, String)>,
         scroll: usize
     },
+    Strings
+    {
+        filter: String,
+        cursor: usize,
+        strings: Vec<usize>,
+        scroll: usize
+    },
     Log(usize),
     Patch
     {
         assembly: String,
         preview: Result<Vec<u8>,String>,
-        cursor: usize
+        diagnostics: Vec<Diagnostic>,
+        cursor: usize,
+        language: Language
+    },
+    PatchSnippet
+    {
+        assembly: String,
+        patch_cursor: usize,
+        language: Language,
+        path: String,
+        cursor: usize,
+        save: bool
     },
     JumpToAddress
     {
         location: String,
         cursor: usize
     },
+    EditValue
+    {
+        field: usize,
+        value: String,
+        cursor: usize
+    },
+    LoadTemplate
+    {
+        path: String,
+        cursor: usize
+    },
+    EditTemplateField
+    {
+        field: usize,
+        value: String,
+        cursor: usize
+    },
+    Copy(usize),
+    EditSymbol
+    {
+        address: u64,
+        value: String,
+        cursor: usize
+    },
+    EditLabel
+    {
+        value: String,
+        cursor: usize
+    },
+    EditComment
+    {
+        value: String,
+        cursor: usize
+    },
+    AddBookmark
+    {
+        name: String,
+        cursor: usize
+    },
+    AddSection
+    {
+        value: String,
+        cursor: usize
+    },
+    SaveAs
+    {
+        path: String,
+        cursor: usize,
+        switch: bool
+    },
+    Export
+    {
+        region: usize,
+        path: String,
+        cursor: usize
+    },
+    Bookmarks(usize),
+    Selection
+    {
+        operation: usize,
+        argument: String,
+        cursor: usize
+    },
     QuitDirtySave(bool),
     SaveAndQuit(bool),
     Save(bool),
@@ -49,9 +138,11 @@ impl <'a> App<'a>
         {
             Some(PopupState::Run{..}) => screen_height - 6 - 2,
             Some(PopupState::FindSymbol{ .. }) => screen_height - 6 - 2,
+            Some(PopupState::Strings{ .. }) => screen_height - 6 - 2,
+            Some(PopupState::Bookmarks(_)) => screen_height - 4 - 2,
             Some(PopupState::Log(_)) => screen_height - 4 - 2,
             Some(PopupState::Help(_)) => screen_height - 4 - 2,
-            Some(PopupState::Patch{..}) => screen_height - 6 - 2,
+            Some(PopupState::Patch{diagnostics, ..}) => screen_height - 7 - PATCH_LISTING_LINES as isize - diagnostics.len().min(PATCH_DIAGNOSTIC_LINES) as isize - 2,
             _ => 0
         };
 
@@ -76,8 +167,12 @@ impl <'a> App<'a>
                 if let AssemblyLine::Instruction(instruction) = old_instruction
                 {
                     let old_bytes_offset = instruction.file_address as usize;
-                    let old_bytes_len = instruction.instruction.len();
+                    let patched_instructions = self.get_patched_instructions(old_bytes_offset, preview.len().max(1));
+                    let old_bytes_len = patched_instructions.last()
+                        .map(|last| (last.file_address + last.instruction.len() as u64) as usize - old_bytes_offset)
+                        .unwrap_or(instruction.instruction.len());
                     let patch_len = preview.len();
+                    let padded_preview = self.get_padded_patch(old_bytes_offset, preview);
                     let max_instruction_length = std::cmp::min(16, self.data.len() - old_bytes_offset);
                     let old_bytes_with_max_possible_length = &self.data[old_bytes_offset..old_bytes_offset + max_instruction_length];
                     for (i, byte) in old_bytes_with_max_possible_length.iter().enumerate()
@@ -95,6 +190,11 @@ impl <'a> App<'a>
                             };
                             preview_string.spans.push(Span::styled(format!("{:02X} ", preview[i]), style));
                         }
+                        else if i < padded_preview.len()
+                        {
+                            let style = color_settings.patch_padding;
+                            preview_string.spans.push(Span::styled(format!("{:02X} ", padded_preview[i]), style));
+                        }
                         else if i < old_bytes_len
                         {
                             let style = color_settings.patch_old_instruction;
@@ -133,11 +233,80 @@ impl <'a> App<'a>
         preview_string
     }
 
+    // the instructions replaced by the patch on the left and the new ones, decoded at the patch address, on the right
+    pub(super) fn get_patch_listing(&self, color_settings: &ColorSettings, preview: &Result<Vec<u8>,String>, width: usize, lines: usize) -> Vec<Line<'a>>
+    {
+        let offset = self.get_patch_offset();
+        let (old_instructions, new_instructions) = match preview
+        {
+            Ok(preview) if !preview.is_empty() =>
+            {
+                let old_instructions: Vec<Instruction> = self.get_patched_instructions(offset, preview.len()).iter()
+                    .map(|old| old.instruction)
+                    .collect();
+                let padded_preview = self.get_padded_patch(offset, preview);
+                let mut decoder = iced_x86::Decoder::new(self.header.bitness(), &padded_preview, iced_x86::DecoderOptions::NONE);
+                decoder.set_ip(self.get_patch_virtual_address());
+                let new_instructions: Vec<Instruction> = decoder.into_iter().collect();
+                (old_instructions, new_instructions)
+            },
+            _ => (Vec::new(), Vec::new()),
+        };
+        let column_width = (width - 3) / 2;
+        let column = |text: &str| format!("{:width$}", text.chars().take(column_width).collect::<String>(), width = column_width);
+        let row = |old: &str, new: &str, old_style, new_style| Line::from(vec![
+            Span::styled(column(old), old_style),
+            Span::styled(" │ ", color_settings.menu_text),
+            Span::styled(column(new), new_style),
+        ]);
+        let format = |instruction: Option<&Instruction>| instruction
+            .map(|instruction| Self::format_instruction(instruction, &self.header, &self.project.labels, self.project.syntax))
+            .unwrap_or_default();
+        let rows = old_instructions.len().max(new_instructions.len());
+        let mut listing = Vec::new();
+        if rows == 0
+        {
+            listing.push(row("Before", "After", color_settings.placeholder, color_settings.placeholder));
+        }
+        for i in 0..rows.min(lines)
+        {
+            if i == lines - 1 && rows > lines
+            {
+                listing.push(row(&format!("{} more", rows - i), "", color_settings.placeholder, color_settings.placeholder));
+                break;
+            }
+            let (old, new) = (old_instructions.get(i), new_instructions.get(i));
+            let (old_text, new_text) = (format(old), format(new));
+            // instructions that the patch leaves as they were are dimmed
+            let unchanged = old.zip(new).is_some_and(|(old, new)| old.ip() == new.ip() && old_text == new_text);
+            let (old_style, new_style) = if unchanged
+            {
+                (color_settings.patch_old_rest, color_settings.patch_old_rest)
+            }
+            else if new.is_some_and(|new| new.is_invalid())
+            {
+                (color_settings.patch_old_instruction, color_settings.assembly_bad)
+            }
+            else
+            {
+                (color_settings.patch_old_instruction, color_settings.patch_patched_less_or_equal)
+            };
+            listing.push(row(&old_text, &new_text, old_style, new_style));
+        }
+        while listing.len() < lines
+        {
+            listing.push(row("", "", color_settings.placeholder, color_settings.placeholder));
+        }
+        listing
+    }
+
     pub(super) fn resize_popup_if_needed(popup: &mut Option<PopupState>)
     {
         match popup
         {
             Some(PopupState::FindSymbol { scroll, .. }) |
+            Some(PopupState::Strings { scroll, .. }) |
+            Some(PopupState::Bookmarks(scroll)) |
             Some(PopupState::Log(scroll)) |
             Some(PopupState::Help(scroll)) =>
             {
@@ -175,7 +344,7 @@ impl <'a> App<'a>
         Line::from(spans)
     }
 
-    pub(super) fn get_multiline_from_string_and_cursor(color_settings: &ColorSettings, s: &str, cursor: usize, placeholder: &str) -> (Vec<Line<'a>>, usize)
+    pub(super) fn get_multiline_from_string_and_cursor(color_settings: &ColorSettings, s: &str, styles: &[Style], cursor: usize, placeholder: &str) -> (Vec<Line<'a>>, usize)
     {
         let string = s.to_string();
         if string.len() == 0
@@ -194,7 +363,7 @@ impl <'a> App<'a>
             }
             else
             {
-                color_settings.menu_text
+                styles.get(i).copied().unwrap_or(color_settings.menu_text)
             };
             if c == '\n'
             {
@@ -219,11 +388,59 @@ impl <'a> App<'a>
         (lines, selected_line)
     }
 
+    // the lines of the patch with errors or warnings are underlined, errors take precedence
+    fn underline_diagnostics(color_settings: &ColorSettings, lines: &mut [Line<'a>], diagnostics: &[Diagnostic])
+    {
+        for level in [DiagnosticLevel::Warning, DiagnosticLevel::Error]
+        {
+            let style = match level
+            {
+                DiagnosticLevel::Error => color_settings.patch_error_line,
+                DiagnosticLevel::Warning => color_settings.patch_warning_line,
+            };
+            for line in diagnostics.iter().filter(|diagnostic| diagnostic.level == level).filter_map(|diagnostic| diagnostic.line)
+            {
+                if let Some(line) = lines.get_mut(line - 1)
+                {
+                    // the first span is the line number
+                    for span in line.spans.iter_mut().skip(1)
+                    {
+                        span.style = span.style.patch(style);
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_diagnostic_lines(color_settings: &ColorSettings, diagnostics: &[Diagnostic], lines: usize) -> Vec<Line<'a>>
+    {
+        let mut diagnostic_lines: Vec<Line> = diagnostics.iter().take(lines).map(|diagnostic|
+        {
+            let (level, style) = match diagnostic.level
+            {
+                DiagnosticLevel::Error => ("Error", color_settings.log_error),
+                DiagnosticLevel::Warning => ("Warning", color_settings.log_warning),
+            };
+            Line::from(vec![
+                Span::styled(format!("{} ", level), style),
+                Span::styled(diagnostic.to_string(), color_settings.log_message),
+            ]).left_aligned()
+        }).collect();
+        if diagnostics.len() > lines
+        {
+            if let Some(last) = diagnostic_lines.last_mut()
+            {
+                *last = Line::from(vec![Span::styled(format!("{} more", diagnostics.len() - lines + 1), color_settings.placeholder)]).left_aligned();
+            }
+        }
+        diagnostic_lines
+    }
+
     pub(super) fn fill_popup(&'a self, color_settings: &ColorSettings, popup_state: &PopupState, f: &Frame, popup_title: &mut &str, popup_text: &mut Text<'a>, popup_rect: &mut Rect) -> Result<(), Box<dyn Error>>
     {
         match &popup_state
         {
-            PopupState::Run { command, cursor, results, scroll } =>
+            PopupState::Run { command, cursor, results, scroll, .. } =>
             {
                 *popup_title = "Run";
                 let width = 60;
@@ -373,6 +590,52 @@ impl <'a> App<'a>
                 }
                 
             }
+            PopupState::Strings { filter, cursor, strings, scroll } =>
+            {
+                *popup_title = "Strings";
+                let width = 80.min(f.size().width as usize - 2);
+                let max_results = self.get_scrollable_popup_line_count()?;
+                let height = max_results + 2 + 4;
+                *popup_rect = Rect::new(f.size().width / 2 - width as u16/2, f.size().height / 2 - height as u16 / 2, width as u16, height as u16);
+                let mut editable_string = Self::get_line_from_string_and_cursor(color_settings, filter, *cursor, "Filter");
+                editable_string.spans.push(Span::styled(format!(" (min {} chars, PgUp/PgDn)", self.strings_min_length), color_settings.placeholder));
+                popup_text.lines.extend(
+                    vec![
+                        editable_string.left_aligned(),
+                        Line::raw("─".repeat(width)),
+                    ]
+                );
+                let skip = 0.max(*scroll as isize - max_results as isize / 2) as usize;
+                let skip = skip.min(strings.len().saturating_sub(max_results));
+                let relative_scroll = *scroll - skip;
+                if skip > 0
+                {
+                    popup_text.lines.push(Line::from(vec![Span::styled("▲", color_settings.menu_text)]));
+                }
+                else
+                {
+                    popup_text.lines.push(Line::raw(""));
+                }
+                if strings.is_empty()
+                {
+                    popup_text.lines.push(Line::raw("No strings found.").left_aligned());
+                }
+                popup_text.lines.extend(
+                    strings.iter()
+                        .skip(skip)
+                        .take(max_results)
+                        .enumerate()
+                        .map(|(i, s)| self.string_to_line(color_settings, &self.strings[*s], relative_scroll == i, width - 2))
+                );
+                if strings.len() as isize - skip as isize > max_results as isize
+                {
+                    popup_text.lines.push(Line::from(vec![Span::styled("▼", color_settings.menu_text)]));
+                }
+                else
+                {
+                    popup_text.lines.push(Line::raw(""));
+                }
+            }
             PopupState::Log(scroll) =>
             {
                 *popup_title = "Log";
@@ -404,22 +667,45 @@ impl <'a> App<'a>
                     }
                 }
             }
-            PopupState::Patch {assembly,preview,  cursor} =>
+            PopupState::Patch {assembly, preview, diagnostics, cursor, language} =>
             {
-                *popup_title = "Patch";
+                *popup_title = match language
+                {
+                    Language::Assembly => "Patch",
+                    Language::C => "Patch C",
+                };
                 let available_editable_text_lines = self.get_scrollable_popup_line_count()?;
-                let height = 6 + available_editable_text_lines as u16;
+                let diagnostic_lines = diagnostics.len().min(PATCH_DIAGNOSTIC_LINES);
+                let height = 7 + PATCH_LISTING_LINES as u16 + diagnostic_lines as u16 + available_editable_text_lines as u16;
 
                 let width = 60;
                 *popup_rect = Rect::new(f.size().width / 2 - width/2, f.size().height / 2 - height/2, width, height);
-                let (editable_lines, selected_line) = Self::get_multiline_from_string_and_cursor(color_settings, assembly, *cursor, "Assembly");
+                let (mut editable_lines, selected_line) = match language
+                {
+                    Language::Assembly => Self::get_multiline_from_string_and_cursor(color_settings, assembly, &Self::get_assembly_styles(color_settings, assembly), *cursor, "Assembly"),
+                    Language::C => Self::get_multiline_from_string_and_cursor(color_settings, assembly, &[], *cursor, "C source"),
+                };
+                Self::underline_diagnostics(color_settings, &mut editable_lines, diagnostics);
                 let preview_line = self.get_patch_preview(color_settings, preview);
-                popup_text.lines.extend(
-                    vec![
-                        preview_line.left_aligned(),
-                        Line::raw("─".repeat(width as usize)),
-                    ]
-                );
+                let patch_offset = self.get_patch_offset();
+                let relocations = match preview
+                {
+                    Ok(preview) => self.count_relocations_in(patch_offset, patch_offset + preview.len()),
+                    Err(_) => 0,
+                };
+                let separator_line = if relocations > 0
+                {
+                    Line::from(vec![Span::styled(format!("{} relocations overlap the patch, ^R removes them", relocations), color_settings.log_warning)])
+                }
+                else
+                {
+                    Line::raw("─".repeat(width as usize))
+                };
+                popup_text.lines.push(preview_line.left_aligned());
+                popup_text.lines.extend(Self::get_diagnostic_lines(color_settings, diagnostics, diagnostic_lines));
+                popup_text.lines.push(separator_line);
+                popup_text.lines.extend(self.get_patch_listing(color_settings, preview, width as usize - 2, PATCH_LISTING_LINES));
+                popup_text.lines.push(Line::raw("─".repeat(width as usize)));
                 let skip_lines = 0.max(selected_line as isize - (available_editable_text_lines as isize - 1) / 2) as usize;
                 let skip_lines = skip_lines.min(editable_lines.len().saturating_sub(available_editable_text_lines as usize));
                 if skip_lines == 0
@@ -450,6 +736,191 @@ impl <'a> App<'a>
                     vec![editable_string.left_aligned()]
                 );
             }
+            PopupState::EditValue {field, value, cursor} =>
+            {
+                *popup_title = "Edit Value";
+                *popup_rect = Rect::new(f.size().width / 2 - 30, f.size().height / 2 - 3, 60, 4);
+                let fields = self.inspector_fields();
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, value, *cursor, "Value");
+                popup_text.lines.extend(
+                    vec![
+                        Line::from(vec![
+                            Span::styled("▲ ", color_settings.menu_text),
+                            Span::styled(fields[*field].name(), color_settings.inspector_type),
+                            Span::styled(" ▼", color_settings.menu_text),
+                        ]),
+                        editable_string.left_aligned()
+                    ]
+                );
+            }
+            PopupState::PatchSnippet {path, cursor, save, ..} =>
+            {
+                *popup_title = if *save { "Save Snippet" } else { "Load Snippet" };
+                *popup_rect = Rect::new(f.size().width / 2 - 30, f.size().height / 2 - 3, 60, 3);
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, path, *cursor, "Path");
+                popup_text.lines.extend(
+                    vec![editable_string.left_aligned()]
+                );
+            }
+            PopupState::LoadTemplate {path, cursor} =>
+            {
+                *popup_title = "Load Template";
+                *popup_rect = Rect::new(f.size().width / 2 - 30, f.size().height / 2 - 3, 60, 3);
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, path, *cursor, "Path");
+                popup_text.lines.extend(
+                    vec![editable_string.left_aligned()]
+                );
+            }
+            PopupState::EditTemplateField {field, value, cursor} =>
+            {
+                *popup_title = "Edit Field";
+                *popup_rect = Rect::new(f.size().width / 2 - 30, f.size().height / 2 - 3, 60, 4);
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, value, *cursor, "Value");
+                if let Some(template) = &self.template
+                {
+                    let field = &template.fields[*field];
+                    popup_text.lines.push(Line::from(vec![
+                        Span::styled(field.name.clone(), color_settings.template_name),
+                        Span::styled(format!(": {}", field.type_name), color_settings.template_type),
+                    ]));
+                }
+                popup_text.lines.push(editable_string.left_aligned());
+            }
+            PopupState::Copy(selected) =>
+            {
+                *popup_title = "Copy";
+                let items = CopyItem::all();
+                let width = 60;
+                let height = items.len() as u16 + 2;
+                *popup_rect = Rect::new(f.size().width / 2 - width / 2, f.size().height / 2 - height / 2, width, height);
+                popup_text.lines.extend(items.iter().enumerate().map(|(i, item)| self.copy_item_to_line(color_settings, *item, i == *selected, width as usize - 2)));
+            }
+            PopupState::EditSymbol {address, value, cursor} =>
+            {
+                *popup_title = "Symbol";
+                *popup_rect = Rect::new(f.size().width / 2 - 30, f.size().height / 2 - 3, 60, 4);
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, value, *cursor, "Name (empty to remove)");
+                popup_text.lines.push(Line::styled(format!("{:#X}", address), color_settings.assembly_address));
+                popup_text.lines.push(editable_string.left_aligned());
+            }
+            PopupState::EditLabel {value, cursor} =>
+            {
+                *popup_title = "Label";
+                *popup_rect = Rect::new(f.size().width / 2 - 30, f.size().height / 2 - 3, 60, 3);
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, value, *cursor, "Label (empty to remove)");
+                popup_text.lines.push(editable_string.left_aligned());
+            }
+            PopupState::EditComment {value, cursor} =>
+            {
+                *popup_title = "Comment";
+                *popup_rect = Rect::new(f.size().width / 2 - 30, f.size().height / 2 - 3, 60, 3);
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, value, *cursor, "Comment (empty to remove)");
+                popup_text.lines.push(editable_string.left_aligned());
+            }
+            PopupState::AddBookmark {name, cursor} =>
+            {
+                *popup_title = "Add Bookmark";
+                *popup_rect = Rect::new(f.size().width / 2 - 30, f.size().height / 2 - 3, 60, 3);
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, name, *cursor, "Name");
+                popup_text.lines.push(editable_string.left_aligned());
+            }
+            PopupState::AddSection {value, cursor} =>
+            {
+                *popup_title = "Add Section";
+                *popup_rect = Rect::new(f.size().width / 2 - 30, f.size().height / 2 - 3, 60, 3);
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, value, *cursor, "Name and size (e.g. .patch 0x1000)");
+                popup_text.lines.push(editable_string.left_aligned());
+            }
+            PopupState::SaveAs {path, cursor, switch} =>
+            {
+                *popup_title = "Save As";
+                *popup_rect = Rect::new(f.size().width / 2 - 30, f.size().height / 2 - 3, 60, 4);
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, path, *cursor, "Path");
+                popup_text.lines.push(editable_string.left_aligned());
+                popup_text.lines.push(Line::from(vec![
+                    Span::styled("Edit the new file: ", color_settings.menu_text),
+                    Span::styled(if *switch { "yes" } else { "no" }, color_settings.menu_text_selected),
+                    Span::styled(" (↑/↓ to change)", color_settings.placeholder),
+                ]).left_aligned());
+            }
+            PopupState::Export {region, path, cursor} =>
+            {
+                *popup_title = "Export";
+                let regions = ExportRegion::all();
+                let width = 60;
+                let height = regions.len() as u16 + 6;
+                *popup_rect = Rect::new(f.size().width / 2 - width / 2, f.size().height / 2 - height / 2, width, height);
+                let (start, end) = self.get_export_range(regions[*region]).unwrap_or_default();
+                popup_text.lines.push(Line::from(vec![
+                    Span::styled(format!("{:#X} - {:#X}", start, end), color_settings.assembly_address),
+                    Span::styled(format!(" ({} bytes)", end - start), color_settings.menu_text),
+                ]));
+                popup_text.lines.push(Line::raw("─".repeat(width as usize)));
+                popup_text.lines.extend(regions.iter().enumerate().map(|(i, r)| r.to_line(color_settings, i == *region)));
+                popup_text.lines.push(Line::raw("─".repeat(width as usize)));
+                let editable_string = Self::get_line_from_string_and_cursor(color_settings, path, *cursor, "Path");
+                popup_text.lines.push(editable_string.left_aligned());
+            }
+            PopupState::Bookmarks(scroll) =>
+            {
+                *popup_title = "Bookmarks";
+                let width = 60;
+                let max_lines = self.get_scrollable_popup_line_count()?;
+                let height = max_lines + 4;
+                *popup_rect = Rect::new(f.size().width / 2 - width as u16 / 2, f.size().height / 2 - height as u16 / 2, width as u16, height as u16);
+                let bookmarks = &self.project.bookmarks;
+                let skip = 0.max(*scroll as isize - max_lines as isize / 2) as usize;
+                let skip = skip.min(bookmarks.len().saturating_sub(max_lines));
+                if skip > 0
+                {
+                    popup_text.lines.push(Line::from(vec![Span::styled("▲", color_settings.menu_text)]));
+                }
+                else
+                {
+                    popup_text.lines.push(Line::raw(""));
+                }
+                if bookmarks.is_empty()
+                {
+                    popup_text.lines.push(Line::raw("No bookmarks, press M to add one.").left_aligned());
+                }
+                popup_text.lines.extend(
+                    bookmarks.iter()
+                        .enumerate()
+                        .skip(skip)
+                        .take(max_lines)
+                        .map(|(i, (offset, name))| self.bookmark_to_line(color_settings, *offset, name, i == *scroll, width - 2))
+                );
+                if bookmarks.len() as isize - skip as isize > max_lines as isize
+                {
+                    popup_text.lines.push(Line::from(vec![Span::styled("▼", color_settings.menu_text)]));
+                }
+                else
+                {
+                    popup_text.lines.push(Line::raw(""));
+                }
+            }
+            PopupState::Selection {operation, argument, cursor} =>
+            {
+                *popup_title = "Selection";
+                let operations = SelectionOperation::all();
+                let width = 60;
+                let height = operations.len() as u16 + 6;
+                *popup_rect = Rect::new(f.size().width / 2 - width / 2, f.size().height / 2 - height / 2, width, height);
+                let (start, end) = self.get_selection();
+                popup_text.lines.push(Line::from(vec![
+                    Span::styled(format!("{:#X} - {:#X}", start, end), color_settings.assembly_address),
+                    Span::styled(format!(" ({} bytes)", end - start + 1), color_settings.menu_text),
+                ]));
+                popup_text.lines.push(Line::raw("─".repeat(width as usize)));
+                popup_text.lines.extend(operations.iter().enumerate().map(|(i, o)| o.to_line(color_settings, i == *operation)));
+                popup_text.lines.push(Line::raw("─".repeat(width as usize)));
+                let editable_string = match operations[*operation].argument_placeholder()
+                {
+                    Some(placeholder) => Self::get_line_from_string_and_cursor(color_settings, argument, *cursor, placeholder),
+                    None => Line::styled("No argument needed", color_settings.placeholder),
+                };
+                popup_text.lines.push(editable_string.left_aligned());
+            }
             PopupState::SaveAndQuit(yes_selected) =>
             {
                 *popup_title = "Save and Quit";
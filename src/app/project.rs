@@ -0,0 +1,253 @@
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, path::{Path, PathBuf}, rc::Rc};
+
+use ratatui::text::{Line, Span};
+use serde::{Deserialize, Serialize};
+
+use crate::asm::syntax::Syntax;
+
+use super::{assembly::AssemblyLine, color_settings::ColorSettings, disassembly::Disassembly, notification::NotificationLevel, App};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Project
+{
+    // labels are keyed by virtual address, comments and bookmarks by file offset
+    #[serde(default)]
+    pub labels: Rc<HashMap<u64, String>>,
+    #[serde(default)]
+    pub comments: HashMap<u64, String>,
+    #[serde(default)]
+    pub bookmarks: BTreeMap<u64, String>,
+    #[serde(default)]
+    pub syntax: Syntax,
+    // ranges of bytes shown as data in the disassembly, keyed by file offset with their length
+    #[serde(default)]
+    pub data_ranges: BTreeMap<u64, u64>,
+    #[serde(default)]
+    pub disassembly: Disassembly,
+    // file offsets where the recursive disassembly starts decoding, in addition to the entry point and the symbols
+    #[serde(default)]
+    pub code_starts: BTreeSet<u64>,
+}
+
+impl Project
+{
+    pub fn path_for(file_path: &Path) -> PathBuf
+    {
+        let mut file_name = file_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".hexpatch.json");
+        file_path.with_file_name(file_name)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String>
+    {
+        if !path.exists()
+        {
+            return Ok(Self::default());
+        }
+        let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&data).map_err(|e| format!("Invalid project file {}: {}", path.to_string_lossy(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String>
+    {
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, data).map_err(|e| e.to_string())
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.labels.is_empty() && self.comments.is_empty() && self.bookmarks.is_empty() && self.syntax == Syntax::default() && self.data_ranges.is_empty()
+            && self.disassembly == Disassembly::default() && self.code_starts.is_empty()
+    }
+
+    pub fn data_range_at(&self, offset: u64) -> Option<(u64, u64)>
+    {
+        self.data_ranges.range(..=offset).next_back()
+            .filter(|(start, len)| offset < start.saturating_add(**len))
+            .map(|(start, len)| (*start, *len))
+    }
+
+    // the new range is merged with the ranges that it overlaps, adjacent ranges are kept apart so that they are shown differently
+    pub fn add_data_range(&mut self, mut from: u64, mut to: u64)
+    {
+        let merged: Vec<(u64, u64)> = self.data_ranges.iter()
+            .filter(|(start, len)| **start < to && start.saturating_add(**len) > from)
+            .map(|(start, len)| (*start, *len))
+            .collect();
+        for (start, len) in merged
+        {
+            self.data_ranges.remove(&start);
+            from = from.min(start);
+            to = to.max(start.saturating_add(len));
+        }
+        self.data_ranges.insert(from, to - from);
+    }
+
+    // the ranges that are only partially removed are split
+    pub fn remove_data_range(&mut self, from: u64, to: u64)
+    {
+        let overlapping: Vec<(u64, u64)> = self.data_ranges.iter()
+            .filter(|(start, len)| **start < to && start.saturating_add(**len) > from)
+            .map(|(start, len)| (*start, *len))
+            .collect();
+        for (start, len) in overlapping
+        {
+            self.data_ranges.remove(&start);
+            if start < from
+            {
+                self.data_ranges.insert(start, from - start);
+            }
+            if start.saturating_add(len) > to
+            {
+                self.data_ranges.insert(to, start.saturating_add(len) - to);
+            }
+        }
+    }
+
+    pub fn label_to_address(&self, label: &str) -> Option<u64>
+    {
+        self.labels.iter().find(|(_, name)| name.as_str() == label).map(|(address, _)| *address)
+    }
+}
+
+impl <'a> App<'a>
+{
+    pub(super) fn save_project(&mut self)
+    {
+        let path = Project::path_for(&self.path);
+        if self.project.is_empty() && !path.exists()
+        {
+            return;
+        }
+        if let Err(e) = self.project.save(&path)
+        {
+            self.log(NotificationLevel::Error, &format!("Could not save the project file: {}", e));
+        }
+    }
+
+    // file offset and virtual address of the instruction at the cursor, or of the byte if there is no instruction
+    pub(super) fn get_cursor_location(&self) -> (u64, u64)
+    {
+        match self.get_current_instruction()
+        {
+            AssemblyLine::Instruction(instruction) => (instruction.file_address, instruction.instruction.ip()),
+            AssemblyLine::Data(data) => (data.file_address, data.virtual_address),
+            AssemblyLine::SectionTag(_) =>
+            {
+                let offset = self.get_cursor_position().global_byte_index as u64;
+                (offset, self.header.physical_to_virtual_address(offset).unwrap_or(offset))
+            }
+        }
+    }
+
+    pub(super) fn get_label_at_cursor(&self) -> String
+    {
+        let (_, address) = self.get_cursor_location();
+        self.project.labels.get(&address).cloned().unwrap_or_default()
+    }
+
+    pub(super) fn set_label_at_cursor(&mut self, label: &str) -> Result<(), String>
+    {
+        let (_, address) = self.get_cursor_location();
+        let label = label.trim();
+        if label.is_empty()
+        {
+            if Rc::make_mut(&mut self.project.labels).remove(&address).is_some()
+            {
+                self.log(NotificationLevel::Info, &format!("Removed label at {:#X}", address));
+            }
+        }
+        else
+        {
+            if label.chars().any(|c| c.is_whitespace())
+            {
+                return Err(format!("Invalid label: \"{}\"", label));
+            }
+            if let Some(other) = self.project.label_to_address(label).filter(|other| *other != address)
+            {
+                return Err(format!("Label {} is already used at {:#X}", label, other));
+            }
+            Rc::make_mut(&mut self.project.labels).insert(address, label.to_string());
+            self.log(NotificationLevel::Info, &format!("Added label {} at {:#X}", label, address));
+        }
+        self.save_project();
+        Ok(())
+    }
+
+    pub(super) fn get_comment_at_cursor(&self) -> String
+    {
+        let (offset, _) = self.get_cursor_location();
+        self.project.comments.get(&offset).cloned().unwrap_or_default()
+    }
+
+    pub(super) fn set_comment_at_cursor(&mut self, comment: &str)
+    {
+        let (offset, _) = self.get_cursor_location();
+        let comment = comment.trim();
+        if comment.is_empty()
+        {
+            self.project.comments.remove(&offset);
+        }
+        else
+        {
+            self.project.comments.insert(offset, comment.to_string());
+        }
+        self.save_project();
+    }
+
+    pub(super) fn add_bookmark_at_cursor(&mut self, name: &str)
+    {
+        let offset = self.get_cursor_position().global_byte_index as u64;
+        let name = if name.trim().is_empty()
+        {
+            format!("{:#X}", offset)
+        }
+        else
+        {
+            name.trim().to_string()
+        };
+        self.log(NotificationLevel::Info, &format!("Added bookmark {} at {:#X}", name, offset));
+        self.project.bookmarks.insert(offset, name);
+        self.save_project();
+    }
+
+    pub(super) fn remove_bookmark(&mut self, index: usize)
+    {
+        if let Some(offset) = self.project.bookmarks.keys().nth(index).cloned()
+        {
+            self.project.bookmarks.remove(&offset);
+            self.save_project();
+        }
+    }
+
+    pub(super) fn jump_to_bookmark(&mut self, index: usize)
+    {
+        if let Some(offset) = self.project.bookmarks.keys().nth(index).cloned()
+        {
+            self.jump_to(offset as usize, false);
+        }
+        else
+        {
+            self.log(NotificationLevel::Error, "No bookmarks found");
+        }
+    }
+
+    pub(super) fn bookmark_to_line(&self, color_settings: &ColorSettings, offset: u64, name: &str, selected: bool, width: usize) -> Line<'a>
+    {
+        let name: String = name.chars().take(width.saturating_sub(18)).collect();
+        let padding = width.saturating_sub(18 + name.chars().count());
+        let (style_name, style_address) = if selected
+        {
+            (color_settings.assembly_selected, color_settings.assembly_selected)
+        }
+        else
+        {
+            (color_settings.assembly_label, color_settings.assembly_address)
+        };
+        Line::from(vec![
+            Span::styled(name, style_name),
+            Span::styled(" ".repeat(padding + 1), style_name),
+            Span::styled(format!("{:16X}", offset), style_address),
+        ]).left_aligned()
+    }
+}
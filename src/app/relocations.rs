@@ -0,0 +1,46 @@
+use super::{notification::NotificationLevel, App};
+
+impl <'a> App<'a>
+{
+    pub(super) fn color_relocations(&mut self)
+    {
+        let ranges: Vec<(usize, usize)> = self.header.get_relocations().iter()
+            .map(|relocation| (relocation.offset as usize, (relocation.offset + relocation.size) as usize))
+            .collect();
+        for (from, to) in ranges
+        {
+            self.restyle_range(from, to);
+        }
+        self.update_cursors();
+    }
+
+    pub(super) fn count_relocations_in(&self, from: usize, to: usize) -> usize
+    {
+        self.header.relocations_in(from as u64, to as u64).len()
+    }
+
+    // the entries are disabled in the relocation table so that the loader does not overwrite the patched bytes
+    pub(super) fn remove_relocations_in(&mut self, from: usize, to: usize)
+    {
+        let relocations = self.header.remove_relocations_in(from as u64, to as u64);
+        if relocations.is_empty()
+        {
+            self.log(NotificationLevel::Info, "There are no relocations to remove");
+            return;
+        }
+        let current = self.get_cursor_position().global_byte_index;
+        for relocation in relocations.iter()
+        {
+            let removal = &relocation.removal;
+            if removal.offset as usize + removal.bytes.len() > self.data.len()
+            {
+                continue;
+            }
+            self.jump_to(removal.offset as usize, false);
+            self.write_bytes(removal.offset as usize, &removal.bytes);
+            self.restyle_range(relocation.offset as usize, (relocation.offset + relocation.size) as usize);
+            self.log(NotificationLevel::Info, &removal.description);
+        }
+        self.jump_to(current, false);
+    }
+}
@@ -1,8 +1,74 @@
-use std::error::Error;
+use std::{error::Error, path::Path};
 
 use ratatui::text::{Line, Span};
 
-use super::{color_settings::ColorSettings, notification::NotificationLevel, App};
+use crate::asm::syntax::Syntax;
+
+use super::{color_settings::ColorSettings, disassembly::Disassembly, notification::NotificationLevel, popup_state::PopupState, selection::parse_hex_bytes, App};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentKind
+{
+    Address,
+    Number,
+    Bytes,
+    Setting,
+    Text,
+    Path,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argument
+{
+    pub name: &'static str,
+    pub kind: ArgumentKind,
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgumentValue
+{
+    Number(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+}
+
+pub const SETTINGS: [&str; 6] = ["block_size", "strings_min_length", "backup", "write_symbols", "syntax", "disassembly"];
+
+pub fn parse_number(number: &str) -> Result<u64, String>
+{
+    let number = number.trim();
+    let value = if let Some(hex) = number.strip_prefix("0x").or_else(|| number.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16)
+    }
+    else
+    {
+        number.parse()
+    };
+    value.map_err(|_| format!("Invalid number: \"{}\"", number))
+}
+
+fn parse_bool(value: &str) -> Result<bool, String>
+{
+    match value.to_lowercase().as_str()
+    {
+        "true" | "on" | "yes" | "1" => Ok(true),
+        "false" | "off" | "no" | "0" => Ok(false),
+        _ => Err(format!("Invalid boolean: \"{}\"", value)),
+    }
+}
+
+fn common_prefix(strings: &[String]) -> String
+{
+    let mut prefix = strings.first().cloned().unwrap_or_default();
+    for string in strings.iter().skip(1)
+    {
+        let length = prefix.chars().zip(string.chars()).take_while(|(a, b)| a == b).map(|(a, _)| a.len_utf8()).sum();
+        prefix.truncate(length);
+    }
+    prefix
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Command
@@ -11,34 +77,197 @@ pub enum Command
     QuitWithoutSave,
     QuitWithSave,
     Save,
+    ForceSave,
+    SaveAs,
+    Export,
+    Backup,
+    WriteSymbols,
+    AddSection,
+    Goto,
+    Fill,
+    Set,
+    Script,
+    SignaturePatch,
+    FindSignature,
+    GenerateSignature,
     Empty,
     Unknown,
 }
 
 impl Command
 {
-    pub fn get_commands() -> Vec<&'static str>
+    pub fn all() -> [Command; 17]
     {
-        vec![
-            "quit",
-            "dquit",
-            "xquit",
-            "save",
+        [
+            Command::Quit,
+            Command::QuitWithoutSave,
+            Command::QuitWithSave,
+            Command::Save,
+            Command::ForceSave,
+            Command::SaveAs,
+            Command::Export,
+            Command::Backup,
+            Command::WriteSymbols,
+            Command::AddSection,
+            Command::Goto,
+            Command::Fill,
+            Command::Set,
+            Command::Script,
+            Command::SignaturePatch,
+            Command::FindSignature,
+            Command::GenerateSignature,
         ]
     }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Command::Quit => "quit",
+            Command::QuitWithoutSave => "dquit",
+            Command::QuitWithSave => "xquit",
+            Command::Save => "save",
+            Command::ForceSave => "fsave",
+            Command::SaveAs => "saveas",
+            Command::Export => "export",
+            Command::Backup => "backup",
+            Command::WriteSymbols => "wsymbols",
+            Command::AddSection => "addsection",
+            Command::Goto => "goto",
+            Command::Fill => "fill",
+            Command::Set => "set",
+            Command::Script => "script",
+            Command::SignaturePatch => "sigpatch",
+            Command::FindSignature => "findsig",
+            Command::GenerateSignature => "signature",
+            Command::Empty => "",
+            Command::Unknown => "Unknown command",
+        }
+    }
+
+    pub fn arguments(&self) -> &'static [Argument]
+    {
+        match self
+        {
+            Command::SaveAs => &[Argument { name: "path", kind: ArgumentKind::Path, optional: true }],
+            Command::AddSection => &[Argument { name: "name", kind: ArgumentKind::Text, optional: true }, Argument { name: "size", kind: ArgumentKind::Number, optional: true }],
+            Command::Goto => &[Argument { name: "location", kind: ArgumentKind::Address, optional: false }],
+            Command::Fill => &[Argument { name: "pattern", kind: ArgumentKind::Bytes, optional: false }, Argument { name: "count", kind: ArgumentKind::Number, optional: true }],
+            Command::Set => &[Argument { name: "setting", kind: ArgumentKind::Setting, optional: false }, Argument { name: "value", kind: ArgumentKind::Text, optional: false }],
+            Command::Script => &[Argument { name: "path", kind: ArgumentKind::Path, optional: false }],
+            Command::SignaturePatch => &[Argument { name: "path", kind: ArgumentKind::Path, optional: false }],
+            Command::FindSignature => &[Argument { name: "signature", kind: ArgumentKind::Text, optional: false }],
+            _ => &[],
+        }
+    }
+
+    pub fn description(&self) -> &'static str
+    {
+        match self
+        {
+            Command::Quit => "Quit the program.",
+            Command::QuitWithoutSave => "Quit the program without saving.",
+            Command::QuitWithSave => "Save and quit the program.",
+            Command::Save => "Save the current file.",
+            Command::ForceSave => "Save the current file even if it changed on disk.",
+            Command::SaveAs => "Save the file to a different path.",
+            Command::Export => "Save the file, a section or the selection to a different path.",
+            Command::Backup => "Toggle saving a .bak copy of the original file.",
+            Command::WriteSymbols => "Toggle saving symbols in the file.",
+            Command::AddSection => "Add an executable section to the file.",
+            Command::Goto => "Jump to an address (0x.. or v0x..), symbol, label or section.",
+            Command::Fill => "Fill count bytes, or the selection, with a hex pattern.",
+            Command::Set => "Change a setting (block_size, strings_min_length, backup, write_symbols, syntax, disassembly).",
+            Command::Script => "Run a Rhai script on the file.",
+            Command::SignaturePatch => "Apply the patches in a JSON file, located by byte signatures.",
+            Command::FindSignature => "Jump to the first match of a byte signature (e.g. 48 8B ?? C3).",
+            Command::GenerateSignature => "Copy a unique signature of the instruction at the cursor.",
+            Command::Empty => "",
+            Command::Unknown => "Unknown command",
+        }
+    }
+
+    pub fn usage(&self) -> String
+    {
+        let mut usage = self.name().to_string();
+        for argument in self.arguments()
+        {
+            if argument.optional
+            {
+                usage.push_str(&format!(" [{}]", argument.name));
+            }
+            else
+            {
+                usage.push_str(&format!(" <{}>", argument.name));
+            }
+        }
+        usage
+    }
+
+    pub fn get_commands() -> Vec<&'static str>
+    {
+        Self::all().iter().map(|command| command.name()).collect()
+    }
+
     pub fn from_string(command: &str) -> Command
     {
         match command
         {
-            "quit" => Command::Quit,
-            "dquit" => Command::QuitWithoutSave,
-            "xquit" => Command::QuitWithSave,
-            "save" => Command::Save,
             "" => Command::Empty,
-            _ => Command::Unknown,
+            command => Self::all().into_iter().find(|c| c.name() == command).unwrap_or(Command::Unknown),
         }
     }
 
+    // splits the input of the palette in the command name and its arguments
+    pub fn split_input(input: &str) -> (&str, &str)
+    {
+        let input = input.trim_start();
+        match input.split_once(char::is_whitespace)
+        {
+            Some((name, arguments)) => (name, arguments.trim_start()),
+            None => (input, ""),
+        }
+    }
+
+    pub fn parse_arguments(&self, arguments: &str) -> Result<Vec<ArgumentValue>, String>
+    {
+        let mut values = Vec::new();
+        let mut rest = arguments.trim();
+        let expected = self.arguments();
+        for (i, argument) in expected.iter().enumerate()
+        {
+            if rest.is_empty()
+            {
+                if argument.optional
+                {
+                    break;
+                }
+                return Err(format!("Missing {}, usage: {}", argument.name, self.usage()));
+            }
+            let is_last = i + 1 == expected.len();
+            let (token, remainder) = if is_last && matches!(argument.kind, ArgumentKind::Text | ArgumentKind::Path)
+            {
+                (rest, "")
+            }
+            else
+            {
+                rest.split_once(char::is_whitespace).unwrap_or((rest, ""))
+            };
+            rest = remainder.trim_start();
+            values.push(match argument.kind
+            {
+                ArgumentKind::Number => ArgumentValue::Number(parse_number(token)?),
+                ArgumentKind::Bytes => ArgumentValue::Bytes(parse_hex_bytes(token)?),
+                _ => ArgumentValue::Text(token.to_string()),
+            });
+        }
+        if !rest.is_empty()
+        {
+            return Err(format!("Too many arguments, usage: {}", self.usage()));
+        }
+        Ok(values)
+    }
+
     pub fn to_line(&self, color_settings: &ColorSettings, selected: bool) -> Line<'static>
     {
         let (s0, s1) = if selected {
@@ -46,55 +275,300 @@ impl Command
         } else {
             (color_settings.command_name, color_settings.command_description)
         };
-        match self
-        {
-            Command::Quit => Line::from(vec![Span::styled("quit", s0), Span::styled(" Quit the program.", s1)]),
-            Command::QuitWithoutSave => Line::from(vec![Span::styled("dquit", s0), Span::styled(" Quit the program without saving.", s1)]),
-            Command::QuitWithSave => Line::from(vec![Span::styled("xquit", s0), Span::styled(" Save and quit the program.", s1)]),
-            Command::Save => Line::from(vec![Span::styled("save", s0), Span::styled(" Save the current file.", s1)]),
-            Command::Empty => Line::from(vec![Span::styled("", s0), Span::styled("", s1)]),
-            Command::Unknown => Line::from(vec![Span::styled("Unknown command", s0), Span::styled(" Unknown command", s1)]),
-        }.left_aligned()
+        let usage = self.usage();
+        let arguments = usage[self.name().len()..].to_string();
+        Line::from(vec![
+            Span::styled(self.name(), s0),
+            Span::styled(arguments, s1),
+            Span::styled(format!(" {}", self.description()), s1),
+        ]).left_aligned()
     }
 }
 
 impl <'a> App<'a>
 {
-    pub(super) fn find_commands(&mut self, command: &str) -> Vec<Command>
+    pub(super) fn find_commands(&mut self, input: &str) -> Vec<Command>
     {
-        let ret = self.commands.fuzzy_search_sorted(command);
-        ret.into_iter().map(|cmd| Command::from_string(&cmd)).collect()
+        let (name, _) = Command::split_input(input);
+        let mut ret: Vec<Command> = self.commands.fuzzy_search_sorted(name).into_iter().map(|cmd| Command::from_string(&cmd)).collect();
+        // a command typed in full must be the first result even if a longer name has the same score
+        if let Some(index) = ret.iter().position(|command| command.name() == name)
+        {
+            let command = ret.remove(index);
+            ret.insert(0, command);
+        }
+        ret
     }
 
-    pub(super) fn run_command(&mut self, command: &str, scroll: usize) -> Result<(), Box<dyn Error>>
+    pub(super) fn run_command(&mut self, input: &str, scroll: usize) -> Result<(), Box<dyn Error>>
     {
-        let command_opt = self.find_commands(command).into_iter().skip(scroll).next();
+        let command_opt = self.find_commands(input).into_iter().nth(scroll);
         let command_enum = command_opt.expect("Scroll out of bounds for run_command.");
-        match command_enum
+        let (_, arguments) = Command::split_input(input);
+        self.add_to_command_history(input);
+        self.run_command_with_arguments(command_enum, arguments)
+    }
+
+    pub(super) fn run_command_with_arguments(&mut self, command: Command, arguments: &str) -> Result<(), Box<dyn Error>>
+    {
+        let arguments = command.parse_arguments(arguments)?;
+        match (command, arguments.as_slice())
         {
-            Command::Quit => {
+            (Command::Quit, _) => {
                 self.quit(None)?;
             }
-            Command::QuitWithoutSave => {
+            (Command::QuitWithoutSave, _) => {
                 self.quit(Some(false))?;
             }
-            Command::QuitWithSave => {
+            (Command::QuitWithSave, _) => {
                 self.quit(Some(true))?;
             }
-            Command::Save => {
+            (Command::Save, _) => {
                 if self.dirty
                 {
                     self.save_data()?;
                 }
             }
-            Command::Empty => {}
-            Command::Unknown => {
-                self.log(NotificationLevel::Error, &format!("Unknown command: \"{}\"", command));
+            (Command::ForceSave, _) => {
+                self.save_data_checked(true)?;
+            }
+            (Command::SaveAs, [ArgumentValue::Text(path)]) => {
+                self.save_as(path, true)?;
+            }
+            (Command::SaveAs, _) => {
+                let path = self.path.to_string_lossy().to_string();
+                self.popup = Some(PopupState::SaveAs { cursor: path.len(), path, switch: true });
+            }
+            (Command::Export, _) => {
+                let path = format!("{}.bin", self.path.to_string_lossy());
+                let region = if self.selection_anchor.is_some() { 2 } else { 0 };
+                self.popup = Some(PopupState::Export { region, cursor: path.len(), path });
+            }
+            (Command::Backup, _) => {
+                self.backup = !self.backup;
+                if self.backup
+                {
+                    self.log(NotificationLevel::Info, "The original file will be copied to a .bak file on the first save.");
+                }
+                else
+                {
+                    self.log(NotificationLevel::Info, "No backup will be saved.");
+                }
+            }
+            (Command::WriteSymbols, _) => {
+                self.write_symbols = !self.write_symbols;
+                if self.write_symbols
+                {
+                    self.log(NotificationLevel::Info, "Renamed symbols will be written to the symbol table on save.");
+                }
+                else
+                {
+                    self.log(NotificationLevel::Info, "Symbols will not be written to the file.");
+                }
+            }
+            (Command::AddSection, [ArgumentValue::Text(name), ArgumentValue::Number(size)]) => {
+                self.add_section(name, *size)?;
+            }
+            (Command::AddSection, _) => {
+                self.popup = Some(PopupState::AddSection { value: String::new(), cursor: 0 });
+            }
+            (Command::Goto, [ArgumentValue::Text(location)]) => {
+                self.jump_to_symbol(location);
+            }
+            (Command::Fill, [ArgumentValue::Bytes(pattern), count @ ..]) => {
+                self.fill(pattern, count.first().and_then(|count| match count
+                {
+                    ArgumentValue::Number(count) => Some(*count as usize),
+                    _ => None,
+                }))?;
+            }
+            (Command::Set, [ArgumentValue::Text(setting), ArgumentValue::Text(value)]) => {
+                self.set_setting(setting, value)?;
+            }
+            (Command::Script, [ArgumentValue::Text(path)]) => {
+                self.run_script(path)?;
+            }
+            (Command::SignaturePatch, [ArgumentValue::Text(path)]) => {
+                self.apply_signature_patches(path)?;
+            }
+            (Command::FindSignature, [ArgumentValue::Text(signature)]) => {
+                self.find_signature(signature)?;
+            }
+            (Command::GenerateSignature, _) => {
+                self.generate_signature_at_cursor()?;
+            }
+            (Command::Empty, _) => {}
+            (Command::Unknown, _) => {
+                self.log(NotificationLevel::Error, "Unknown command");
+            }
+            (command, _) => {
+                self.log(NotificationLevel::Error, &format!("Invalid arguments, usage: {}", command.usage()));
             }
         }
         Ok(())
     }
 
+    fn add_to_command_history(&mut self, input: &str)
+    {
+        let input = input.trim();
+        if !input.is_empty() && self.command_history.last().map(|last| last.as_str()) != Some(input)
+        {
+            self.command_history.push(input.to_string());
+            if self.command_history.len() > 100
+            {
+                self.command_history.remove(0);
+            }
+        }
+    }
+
+    // the index counts back from the last command, 0 is the empty input
+    pub(super) fn get_command_from_history(&self, index: usize) -> Option<String>
+    {
+        if index == 0
+        {
+            Some(String::new())
+        }
+        else
+        {
+            self.command_history.len().checked_sub(index).map(|i| self.command_history[i].clone())
+        }
+    }
+
+    fn fill(&mut self, pattern: &[u8], count: Option<usize>) -> Result<(), String>
+    {
+        let (start, end) = self.get_selection();
+        let count = match count
+        {
+            Some(count) => count,
+            None if self.selection_anchor.is_some() => end - start + 1,
+            None => pattern.len(),
+        };
+        let count = count.min(self.data.len() - start);
+        if count == 0 || pattern.is_empty()
+        {
+            return Err("Nothing to fill".to_string());
+        }
+        let bytes: Vec<u8> = pattern.iter().cycle().take(count).cloned().collect();
+        self.write_bytes(start, &bytes);
+        self.log(NotificationLevel::Info, &format!("Filled {} bytes at {:#X}", count, start));
+        Ok(())
+    }
+
+    fn set_setting(&mut self, setting: &str, value: &str) -> Result<(), String>
+    {
+        match setting
+        {
+            "block_size" =>
+            {
+                let block_size = parse_number(value)? as usize;
+                let blocks_per_row = if (1..=64).contains(&block_size) { Self::calc_blocks_per_row(block_size, self.screen_size.0) } else { 0 };
+                if blocks_per_row == 0
+                {
+                    return Err(format!("Invalid block size: {}", value));
+                }
+                self.block_size = block_size;
+                self.resize(blocks_per_row);
+            },
+            "strings_min_length" => self.strings_min_length = (parse_number(value)? as usize).max(1),
+            "backup" => self.backup = parse_bool(value)?,
+            "write_symbols" => self.write_symbols = parse_bool(value)?,
+            "syntax" =>
+            {
+                self.project.syntax = Syntax::from_name(value).ok_or(format!("Unknown syntax: {}, expected one of {}", value,
+                    Syntax::all().map(|syntax| syntax.name()).join(", ")))?;
+                self.save_project();
+            },
+            "disassembly" =>
+            {
+                self.project.disassembly = Disassembly::from_name(value).ok_or(format!("Unknown disassembly: {}, expected one of {}", value,
+                    Disassembly::all().map(|disassembly| disassembly.name()).join(", ")))?;
+                self.disassemble();
+                self.save_project();
+            },
+            _ => return Err(format!("Unknown setting: {}", setting)),
+        }
+        self.log(NotificationLevel::Info, &format!("Set {} to {}", setting, value));
+        Ok(())
+    }
+
+    fn get_argument_candidates(&self, kind: ArgumentKind, partial: &str) -> Vec<String>
+    {
+        let mut candidates: Vec<String> = match kind
+        {
+            ArgumentKind::Address =>
+            {
+                let mut names: Vec<String> = self.project.labels.values().cloned().collect();
+                if let Some(symbols) = self.header.get_symbols()
+                {
+                    names.extend(symbols.values().cloned());
+                }
+                names.extend(self.header.get_sections().into_iter().map(|section| section.name));
+                names
+            },
+            ArgumentKind::Setting => SETTINGS.iter().map(|setting| setting.to_string()).collect(),
+            ArgumentKind::Path =>
+            {
+                let (directory, _) = partial.rsplit_once('/').map(|(directory, file)| (format!("{}/", directory), file)).unwrap_or((String::new(), partial));
+                let entries = std::fs::read_dir(if directory.is_empty() { Path::new(".") } else { Path::new(&directory) });
+                entries.into_iter().flatten().flatten().map(|entry|
+                {
+                    let separator = if entry.path().is_dir() { "/" } else { "" };
+                    format!("{}{}{}", directory, entry.file_name().to_string_lossy(), separator)
+                }).collect()
+            },
+            _ => Vec::new(),
+        };
+        candidates.retain(|candidate| candidate.starts_with(partial));
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+
+    // completes the command name or the argument being typed
+    pub(super) fn complete_command(&mut self, input: &str, scroll: usize) -> String
+    {
+        let (name, arguments) = Command::split_input(input);
+        let command = match self.find_commands(input).into_iter().nth(scroll)
+        {
+            Some(command) => command,
+            None => return input.to_string(),
+        };
+        if !input.trim_start().contains(char::is_whitespace)
+        {
+            return format!("{} ", command.name());
+        }
+        if command.name() != name
+        {
+            return input.to_string();
+        }
+        let mut index = arguments.split_whitespace().count();
+        let partial = if arguments.is_empty() || arguments.ends_with(char::is_whitespace)
+        {
+            ""
+        }
+        else
+        {
+            index -= 1;
+            arguments.split_whitespace().last().unwrap_or_default()
+        };
+        let argument = match command.arguments().get(index)
+        {
+            Some(argument) => argument,
+            None => return input.to_string(),
+        };
+        let candidates = self.get_argument_candidates(argument.kind, partial);
+        if candidates.is_empty()
+        {
+            return input.to_string();
+        }
+        let mut completed = format!("{}{}", &input[..input.len() - partial.len()], common_prefix(&candidates));
+        if candidates.len() == 1 && !completed.ends_with('/')
+        {
+            completed.push(' ');
+        }
+        completed
+    }
+
     pub(super) fn quit(&mut self, save: Option<bool>) -> Result<(), Box<dyn Error>>
     {
         match save
@@ -0,0 +1,261 @@
+use std::{cell::RefCell, collections::HashMap, path::Path, rc::Rc};
+
+use rhai::{Array, Blob, Dynamic, Engine, EvalAltResult, Map};
+
+use crate::{asm::{assembler::assemble, syntax::Syntax}, headers::{elf::Endianness, header::Header}};
+
+use super::{notification::NotificationLevel, App};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+// scripts work on a copy of the file, the changes are applied only if the script succeeds
+struct ScriptState
+{
+    data: Vec<u8>,
+    header: Header,
+    labels: Rc<HashMap<u64, String>>,
+    syntax: Syntax,
+    jump: Option<usize>,
+    messages: Vec<(NotificationLevel, String)>,
+}
+
+impl ScriptState
+{
+    fn range(&self, offset: i64, size: i64) -> ScriptResult<std::ops::Range<usize>>
+    {
+        match offset.checked_add(size)
+        {
+            Some(end) if offset >= 0 && size >= 0 && end as u64 <= self.data.len() as u64 => Ok(offset as usize..end as usize),
+            _ => Err(format!("Range {:#X}+{:#X} is outside the file", offset, size).into()),
+        }
+    }
+
+    fn read_int(&self, offset: i64, size: i64) -> ScriptResult<i64>
+    {
+        let range = self.range(offset, size)?;
+        let mut bytes = [0u8; 8];
+        match self.header.endianness()
+        {
+            Endianness::Little => bytes[..size as usize].copy_from_slice(&self.data[range]),
+            Endianness::Big => bytes[8 - size as usize..].copy_from_slice(&self.data[range]),
+        }
+        Ok(match self.header.endianness()
+        {
+            Endianness::Little => u64::from_le_bytes(bytes),
+            Endianness::Big => u64::from_be_bytes(bytes),
+        } as i64)
+    }
+
+    fn write_int(&mut self, offset: i64, size: i64, value: i64) -> ScriptResult<()>
+    {
+        let range = self.range(offset, size)?;
+        let bytes = match self.header.endianness()
+        {
+            Endianness::Little => (value as u64).to_le_bytes()[..size as usize].to_vec(),
+            Endianness::Big => (value as u64).to_be_bytes()[8 - size as usize..].to_vec(),
+        };
+        self.data[range].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn assemble_at(&self, offset: i64, asm: &str) -> ScriptResult<Vec<u8>>
+    {
+        let offset = unsigned(offset, "offset")?;
+        let address = self.header.physical_to_virtual_address(offset).unwrap_or(offset);
+        let symbols = App::get_assembly_symbols(asm, &self.header, &self.labels);
+        assemble(asm, self.header.bitness(), address, self.syntax, &symbols).map_err(|e| e.to_string().into())
+    }
+}
+
+// offsets and addresses are passed as i64, negative values are rejected instead of wrapping around
+fn unsigned(value: i64, what: &str) -> ScriptResult<u64>
+{
+    u64::try_from(value).map_err(|_| format!("Invalid {}: {}", what, value).into())
+}
+
+fn optional_address(address: Option<u64>) -> Dynamic
+{
+    address.map(|address| Dynamic::from(address as i64)).unwrap_or(Dynamic::UNIT)
+}
+
+fn register_api(engine: &mut Engine, state: &Rc<RefCell<ScriptState>>)
+{
+    let s = state.clone();
+    engine.register_fn("data_len", move || s.borrow().data.len() as i64);
+    let s = state.clone();
+    engine.register_fn("read", move |offset: i64, size: i64| -> ScriptResult<Blob>
+    {
+        let state = s.borrow();
+        Ok(state.data[state.range(offset, size)?].to_vec())
+    });
+    let s = state.clone();
+    engine.register_fn("write", move |offset: i64, bytes: Blob| -> ScriptResult<()>
+    {
+        let mut state = s.borrow_mut();
+        let range = state.range(offset, bytes.len() as i64)?;
+        state.data[range].copy_from_slice(&bytes);
+        Ok(())
+    });
+    for (name, size) in [("u8", 1), ("u16", 2), ("u32", 4), ("u64", 8)]
+    {
+        let s = state.clone();
+        engine.register_fn(format!("read_{}", name), move |offset: i64| s.borrow().read_int(offset, size));
+        let s = state.clone();
+        engine.register_fn(format!("write_{}", name), move |offset: i64, value: i64| s.borrow_mut().write_int(offset, size, value));
+    }
+    let s = state.clone();
+    engine.register_fn("find", move |pattern: Blob, start: i64| -> i64
+    {
+        let state = s.borrow();
+        if pattern.is_empty() || start < 0
+        {
+            return -1;
+        }
+        state.data.windows(pattern.len()).skip(start as usize).position(|window| window == pattern.as_slice())
+            .map(|position| position as i64 + start).unwrap_or(-1)
+    });
+
+    let s = state.clone();
+    engine.register_fn("sections", move || -> Array
+    {
+        s.borrow().header.get_sections().into_iter().map(|section|
+        {
+            let mut map = Map::new();
+            map.insert("name".into(), section.name.into());
+            map.insert("offset".into(), (section.address as i64).into());
+            map.insert("address".into(), (section.virtual_address as i64).into());
+            map.insert("size".into(), (section.size as i64).into());
+            Dynamic::from_map(map)
+        }).collect()
+    });
+    let s = state.clone();
+    engine.register_fn("symbols", move || -> Map
+    {
+        let state = s.borrow();
+        let mut map = Map::new();
+        if let Some(symbols) = state.header.get_symbols()
+        {
+            for (address, name) in symbols.iter()
+            {
+                map.insert(name.as_str().into(), (*address as i64).into());
+            }
+        }
+        map
+    });
+    let s = state.clone();
+    engine.register_fn("symbol", move |name: &str| optional_address(s.borrow().header.symbol_to_address(name)));
+    let s = state.clone();
+    engine.register_fn("to_virtual", move |offset: i64| -> ScriptResult<Dynamic>
+    {
+        Ok(optional_address(s.borrow().header.physical_to_virtual_address(unsigned(offset, "offset")?)))
+    });
+    let s = state.clone();
+    engine.register_fn("to_physical", move |address: i64| -> ScriptResult<Dynamic>
+    {
+        Ok(optional_address(s.borrow().header.virtual_to_physical_address(unsigned(address, "address")?)))
+    });
+
+    let s = state.clone();
+    engine.register_fn("assemble", move |asm: &str, address: i64| -> ScriptResult<Blob>
+    {
+        let state = s.borrow();
+        let symbols = App::get_assembly_symbols(asm, &state.header, &state.labels);
+        assemble(asm, state.header.bitness(), unsigned(address, "address")?, state.syntax, &symbols).map_err(|e| e.to_string().into())
+    });
+    let s = state.clone();
+    engine.register_fn("patch", move |offset: i64, asm: &str| -> ScriptResult<i64>
+    {
+        let bytes = s.borrow().assemble_at(offset, asm)?;
+        let mut state = s.borrow_mut();
+        let range = state.range(offset, bytes.len() as i64)?;
+        state.data[range].copy_from_slice(&bytes);
+        Ok(bytes.len() as i64)
+    });
+    let s = state.clone();
+    engine.register_fn("disassemble", move |offset: i64, size: i64| -> ScriptResult<Array>
+    {
+        let state = s.borrow();
+        let range = state.range(offset, size)?;
+        let address = state.header.physical_to_virtual_address(offset as u64).unwrap_or(offset as u64);
+        let mut decoder = iced_x86::Decoder::new(state.header.bitness(), &state.data[range], iced_x86::DecoderOptions::NONE);
+        decoder.set_ip(address);
+        Ok(decoder.into_iter().map(|instruction|
+        {
+            let mut map = Map::new();
+            map.insert("offset".into(), (offset + (instruction.ip() - address) as i64).into());
+            map.insert("address".into(), (instruction.ip() as i64).into());
+            map.insert("size".into(), (instruction.len() as i64).into());
+            map.insert("text".into(), App::format_instruction(&instruction, &state.header, &state.labels, state.syntax).into());
+            Dynamic::from_map(map)
+        }).collect())
+    });
+
+    let s = state.clone();
+    engine.register_fn("jump", move |offset: i64| s.borrow_mut().jump = Some(offset.max(0) as usize));
+    let s = state.clone();
+    engine.register_fn("log", move |message: &str| s.borrow_mut().messages.push((NotificationLevel::Info, message.to_string())));
+    let s = state.clone();
+    engine.register_fn("warn", move |message: &str| s.borrow_mut().messages.push((NotificationLevel::Warning, message.to_string())));
+    let s = state.clone();
+    engine.on_print(move |message| s.borrow_mut().messages.push((NotificationLevel::Info, message.to_string())));
+    let s = state.clone();
+    engine.on_debug(move |message, _, _| s.borrow_mut().messages.push((NotificationLevel::Debug, message.to_string())));
+}
+
+impl <'a> App<'a>
+{
+    pub(super) fn run_script(&mut self, path: &str) -> Result<(), String>
+    {
+        let script = std::fs::read_to_string(path.trim()).map_err(|e| format!("Could not read {}: {}", path.trim(), e))?;
+        let state = Rc::new(RefCell::new(ScriptState
+        {
+            data: self.data.clone(),
+            header: self.header.clone(),
+            labels: self.project.labels.clone(),
+            syntax: self.project.syntax,
+            jump: None,
+            messages: Vec::new(),
+        }));
+        let mut engine = Engine::new();
+        register_api(&mut engine, &state);
+        let result = engine.run(&script);
+        drop(engine);
+
+        let state = Rc::try_unwrap(state).map_err(|_| "The script state is still in use")?.into_inner();
+        for (level, message) in state.messages
+        {
+            self.log(level, &message);
+        }
+        if let Err(e) = result
+        {
+            return Err(format!("Script {} failed, no changes were applied: {}", path.trim(), e));
+        }
+        let changed = state.data.iter().zip(self.data.iter()).filter(|(a, b)| a != b).count() + state.data.len().abs_diff(self.data.len());
+        if changed > 0
+        {
+            self.replace_data(state.data);
+        }
+        self.log(NotificationLevel::Info, &format!("Script {} changed {} bytes", path.trim(), changed));
+        if let Some(offset) = state.jump
+        {
+            self.jump_to(offset, false);
+        }
+        Ok(())
+    }
+
+    // runs a script without the user interface, the file is saved if the script changed it
+    pub fn run_headless(&mut self, script: &Path) -> Result<(), String>
+    {
+        self.headless = true;
+        let mut result = self.run_script(&script.to_string_lossy());
+        if result.is_ok() && self.dirty
+        {
+            result = self.save_data().map_err(|e| e.to_string());
+        }
+        for line in self.log.iter().filter(|line| line.level != NotificationLevel::Debug)
+        {
+            eprintln!("{} {}", line.level, line.message);
+        }
+        result
+    }
+}
@@ -0,0 +1,39 @@
+use crate::headers::header::Header;
+
+use super::{notification::NotificationLevel, App};
+
+impl <'a> App<'a>
+{
+    // replaces the whole file, used when the headers have changed and everything must be parsed again
+    pub(super) fn replace_data(&mut self, data: Vec<u8>)
+    {
+        let symbol_edits = self.get_symbol_edits();
+        self.data = data;
+        self.header = Header::parse_header(&self.data);
+        self.apply_symbol_edits(symbol_edits);
+        let (assembly_offsets, assembly_instructions) = Self::sections_from_bytes(&self.data, &self.header, &self.project);
+        self.assembly_offsets = assembly_offsets;
+        self.assembly_instructions = assembly_instructions;
+        self.selection_anchor = None;
+        self.dirty = true;
+        self.resize(self.blocks_per_row);
+    }
+
+    pub(super) fn add_section(&mut self, name: &str, size: u64) -> Result<(), String>
+    {
+        if size == 0
+        {
+            return Err("The section size must not be zero".to_string());
+        }
+        let (data, code_offset) = self.header.add_section(&self.data, name, size)?;
+        let added_bytes = data.len() - self.data.len();
+        self.replace_data(data);
+        self.log(NotificationLevel::Info, &format!("Added section {} with {:#X} bytes at {:#X} ({:#X} bytes added to the file)", name, size, code_offset, added_bytes));
+        if let Some(address) = self.header.physical_to_virtual_address(code_offset)
+        {
+            self.log(NotificationLevel::Info, &format!("Section {} is mapped at {:#X}", name, address));
+        }
+        self.jump_to(code_offset as usize, false);
+        Ok(())
+    }
+}
@@ -0,0 +1,404 @@
+use base64::Engine;
+use crossterm::event::KeyModifiers;
+use ratatui::text::{Line, Span};
+
+use crate::asm::nop::nops;
+
+use super::{color_settings::ColorSettings, info_mode::InfoMode, notification::NotificationLevel, App};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionOperation
+{
+    Fill,
+    NopFill,
+    CopyHex,
+    CopyC,
+    CopyPython,
+    CopyBase64,
+    Paste,
+    Xor,
+    Add,
+    Save,
+}
+
+impl SelectionOperation
+{
+    pub fn all() -> [SelectionOperation; 10]
+    {
+        [
+            SelectionOperation::Fill,
+            SelectionOperation::NopFill,
+            SelectionOperation::CopyHex,
+            SelectionOperation::CopyC,
+            SelectionOperation::CopyPython,
+            SelectionOperation::CopyBase64,
+            SelectionOperation::Paste,
+            SelectionOperation::Xor,
+            SelectionOperation::Add,
+            SelectionOperation::Save,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            SelectionOperation::Fill => "fill",
+            SelectionOperation::NopFill => "nop",
+            SelectionOperation::CopyHex => "copy hex",
+            SelectionOperation::CopyC => "copy c",
+            SelectionOperation::CopyPython => "copy python",
+            SelectionOperation::CopyBase64 => "copy base64",
+            SelectionOperation::Paste => "paste",
+            SelectionOperation::Xor => "xor",
+            SelectionOperation::Add => "add",
+            SelectionOperation::Save => "save",
+        }
+    }
+
+    pub fn description(&self) -> &'static str
+    {
+        match self
+        {
+            SelectionOperation::Fill => "Fill with a byte pattern.",
+            SelectionOperation::NopFill => "Fill with NOP instructions.",
+            SelectionOperation::CopyHex => "Copy as hex string.",
+            SelectionOperation::CopyC => "Copy as C array.",
+            SelectionOperation::CopyPython => "Copy as Python bytes.",
+            SelectionOperation::CopyBase64 => "Copy as base64.",
+            SelectionOperation::Paste => "Paste hex, C, Python or base64 bytes.",
+            SelectionOperation::Xor => "XOR with a key.",
+            SelectionOperation::Add => "Add a key to each byte.",
+            SelectionOperation::Save => "Save to a file.",
+        }
+    }
+
+    pub fn argument_placeholder(&self) -> Option<&'static str>
+    {
+        match self
+        {
+            SelectionOperation::Fill => Some("Pattern (e.g. 90 CC)"),
+            SelectionOperation::Paste => Some("Bytes (empty to use the clipboard)"),
+            SelectionOperation::Xor | SelectionOperation::Add => Some("Key (e.g. 0x41 or DE AD)"),
+            SelectionOperation::Save => Some("Path"),
+            _ => None,
+        }
+    }
+
+    pub fn to_line(&self, color_settings: &ColorSettings, selected: bool) -> Line<'static>
+    {
+        let (s0, s1) = if selected {
+            (color_settings.command_selected, color_settings.command_selected)
+        } else {
+            (color_settings.command_name, color_settings.command_description)
+        };
+        Line::from(vec![Span::styled(self.name(), s0), Span::styled(format!(" {}", self.description()), s1)]).left_aligned()
+    }
+}
+
+pub fn bytes_to_hex(bytes: &[u8]) -> String
+{
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
+
+pub fn bytes_to_c_array(bytes: &[u8]) -> String
+{
+    let values = bytes.iter().map(|b| format!("0x{:02X}", b)).collect::<Vec<_>>().join(", ");
+    format!("unsigned char data[{}] = {{ {} }};", bytes.len(), values)
+}
+
+pub fn bytes_to_python(bytes: &[u8]) -> String
+{
+    format!("b'{}'", bytes.iter().map(|b| format!("\\x{:02x}", b)).collect::<String>())
+}
+
+pub fn bytes_to_base64(bytes: &[u8]) -> String
+{
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+fn parse_python_bytes(string: &str) -> Result<Vec<u8>, String>
+{
+    let inner = &string[2..string.len() - 1];
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next()
+    {
+        if c != '\\'
+        {
+            if !c.is_ascii()
+            {
+                return Err(format!("Invalid character in bytes literal: {}", c));
+            }
+            bytes.push(c as u8);
+            continue;
+        }
+        match chars.next()
+        {
+            Some('x') =>
+            {
+                let hex: String = chars.by_ref().take(2).collect();
+                bytes.push(u8::from_str_radix(&hex, 16).map_err(|_| format!("Invalid escape: \\x{}", hex))?);
+            },
+            Some('n') => bytes.push(b'\n'),
+            Some('r') => bytes.push(b'\r'),
+            Some('t') => bytes.push(b'\t'),
+            Some('0') => bytes.push(0),
+            Some(c) if c.is_ascii() => bytes.push(c as u8),
+            _ => return Err("Invalid escape in bytes literal".to_string()),
+        }
+    }
+    Ok(bytes)
+}
+
+fn parse_c_array(string: &str) -> Result<Vec<u8>, String>
+{
+    let inner = match (string.find('{'), string.rfind('}'))
+    {
+        (Some(start), Some(end)) if start < end => &string[start + 1..end],
+        _ => string,
+    };
+    inner.split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            let number = if let Some(hex) = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X"))
+            {
+                u8::from_str_radix(hex, 16)
+            }
+            else
+            {
+                value.parse::<u8>()
+            };
+            number.map_err(|_| format!("Invalid byte in C array: {}", value))
+        })
+        .collect()
+}
+
+pub fn parse_hex_bytes(string: &str) -> Result<Vec<u8>, String>
+{
+    let digits: String = string.split_whitespace()
+        .map(|token| token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token))
+        .collect();
+    if digits.is_empty() || digits.len() & 1 == 1 || !digits.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return Err(format!("Invalid hex bytes: {}", string));
+    }
+    (0..digits.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+pub fn parse_bytes(string: &str) -> Result<Vec<u8>, String>
+{
+    let string = string.trim();
+    if (string.starts_with("b'") && string.ends_with('\'') || string.starts_with("b\"") && string.ends_with('"')) && string.len() >= 3
+    {
+        parse_python_bytes(string)
+    }
+    else if string.contains(',') || string.contains('{')
+    {
+        parse_c_array(string)
+    }
+    else if let Ok(bytes) = parse_hex_bytes(string)
+    {
+        Ok(bytes)
+    }
+    else
+    {
+        base64::engine::general_purpose::STANDARD.decode(string)
+            .map_err(|_| "Unrecognized format, expected hex, C array, Python bytes or base64".to_string())
+    }
+}
+
+impl <'a> App<'a>
+{
+    // inclusive range of the selected bytes, the byte under the cursor if there is no selection
+    pub(super) fn get_selection(&self) -> (usize, usize)
+    {
+        let cursor = self.get_cursor_position().global_byte_index;
+        match self.selection_anchor
+        {
+            Some(anchor) => (anchor.min(cursor), anchor.max(cursor)),
+            None => (cursor, cursor),
+        }
+    }
+
+    pub(super) fn is_selected(&self, index: usize) -> bool
+    {
+        if let Some((start, end)) = self.selection_highlight
+        {
+            index >= start && index <= end
+        }
+        else
+        {
+            false
+        }
+    }
+
+    pub(super) fn start_selection(&mut self)
+    {
+        if self.selection_anchor.is_none()
+        {
+            self.selection_anchor = Some(self.get_cursor_position().global_byte_index);
+        }
+    }
+
+    pub(super) fn clear_selection(&mut self)
+    {
+        if self.selection_anchor.is_some()
+        {
+            self.selection_anchor = None;
+            self.update_cursors();
+        }
+    }
+
+    pub(super) fn update_selection_for_modifiers(&mut self, modifiers: KeyModifiers)
+    {
+        if modifiers.contains(KeyModifiers::SHIFT)
+        {
+            self.start_selection();
+        }
+        else
+        {
+            self.clear_selection();
+        }
+    }
+
+    pub(super) fn byte_at_screen_position(&self, x: u16, y: u16) -> Option<usize>
+    {
+        if y < 1
+        {
+            return None;
+        }
+        let (x, line) = (x as usize, self.scroll + y as usize - 1);
+        let hex_start = 18;
+        let hex_width = self.block_size * 3 * self.blocks_per_row + self.blocks_per_row;
+        let text_start = 17 + hex_width;
+        let (block, byte) = if x >= hex_start && x < text_start - 1
+        {
+            let block_width = self.block_size * 3 + 1;
+            ((x - hex_start) / block_width, (x - hex_start) % block_width / 3)
+        }
+        else if x >= text_start && self.info_mode == InfoMode::Text
+        {
+            let block_width = self.block_size * 2 + 1;
+            ((x - text_start) / block_width, (x - text_start) % block_width / 2)
+        }
+        else
+        {
+            return None;
+        };
+        if block >= self.blocks_per_row
+        {
+            return None;
+        }
+        let index = (line * self.blocks_per_row + block) * self.block_size + byte.min(self.block_size - 1);
+        if index < self.data.len()
+        {
+            Some(index)
+        }
+        else
+        {
+            None
+        }
+    }
+
+    pub(super) fn update_selection_highlight(&mut self)
+    {
+        let new_selection = self.selection_anchor.map(|_| self.get_selection());
+        let old_selection = self.selection_highlight;
+        if new_selection == old_selection
+        {
+            return;
+        }
+        self.selection_highlight = new_selection;
+        match (old_selection, new_selection)
+        {
+            (Some((old_start, old_end)), Some((new_start, new_end))) =>
+            {
+                self.restyle_range(old_start.min(new_start), old_start.max(new_start) + 1);
+                self.restyle_range(old_end.min(new_end), old_end.max(new_end) + 1);
+            },
+            (Some((start, end)), None) | (None, Some((start, end))) =>
+            {
+                self.restyle_range(start, end + 1);
+            },
+            (None, None) => {}
+        }
+    }
+
+    pub(super) fn run_selection_operation(&mut self, operation: SelectionOperation, argument: &str) -> Result<(), String>
+    {
+        if self.data.is_empty()
+        {
+            return Err("Nothing is selected, the file is empty".to_string());
+        }
+        let (start, end) = self.get_selection();
+        let selected = self.data[start..=end].to_vec();
+        match operation
+        {
+            SelectionOperation::Fill =>
+            {
+                let pattern = parse_hex_bytes(argument)?;
+                let bytes: Vec<u8> = pattern.iter().cycle().take(selected.len()).cloned().collect();
+                self.write_bytes(start, &bytes);
+                self.log(NotificationLevel::Info, &format!("Filled {} bytes at {:#X}", bytes.len(), start));
+            },
+            SelectionOperation::NopFill =>
+            {
+                self.write_bytes(start, &nops(self.header.bitness(), selected.len()));
+                self.log(NotificationLevel::Info, &format!("Filled {} bytes at {:#X} with NOPs", selected.len(), start));
+            },
+            SelectionOperation::CopyHex => self.copy_to_clipboard(bytes_to_hex(&selected), "hex"),
+            SelectionOperation::CopyC => self.copy_to_clipboard(bytes_to_c_array(&selected), "C array"),
+            SelectionOperation::CopyPython => self.copy_to_clipboard(bytes_to_python(&selected), "Python bytes"),
+            SelectionOperation::CopyBase64 => self.copy_to_clipboard(bytes_to_base64(&selected), "base64"),
+            SelectionOperation::Paste =>
+            {
+                let source = if argument.trim().is_empty()
+                {
+                    self.clipboard.clone().ok_or("The clipboard is empty")?
+                }
+                else
+                {
+                    argument.to_string()
+                };
+                let mut bytes = parse_bytes(&source)?;
+                // the bytes that do not fit in the selection are left out, without a selection they are all pasted at the cursor
+                if self.selection_anchor.is_some() && bytes.len() > selected.len()
+                {
+                    self.log(NotificationLevel::Warning, &format!("Only {} of {} bytes fit in the selection", selected.len(), bytes.len()));
+                    bytes.truncate(selected.len());
+                }
+                if start + bytes.len() > self.data.len()
+                {
+                    return Err(format!("Pasting {} bytes at {:#X} would go past the end of the file", bytes.len(), start));
+                }
+                self.write_bytes(start, &bytes);
+                self.log(NotificationLevel::Info, &format!("Pasted {} bytes at {:#X}", bytes.len(), start));
+            },
+            SelectionOperation::Xor | SelectionOperation::Add =>
+            {
+                let key = parse_hex_bytes(argument)?;
+                let bytes: Vec<u8> = selected.iter().zip(key.iter().cycle()).map(|(byte, key)| match operation
+                {
+                    SelectionOperation::Xor => byte ^ key,
+                    _ => byte.wrapping_add(*key),
+                }).collect();
+                self.write_bytes(start, &bytes);
+                self.log(NotificationLevel::Info, &format!("Applied {} to {} bytes at {:#X}", operation.name(), bytes.len(), start));
+            },
+            SelectionOperation::Save =>
+            {
+                if argument.is_empty()
+                {
+                    return Err("No path specified".to_string());
+                }
+                std::fs::write(argument, &selected).map_err(|e| e.to_string())?;
+                self.log(NotificationLevel::Info, &format!("Saved {} bytes to {}", selected.len(), argument));
+            },
+        }
+        Ok(())
+    }
+}
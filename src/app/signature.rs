@@ -0,0 +1,238 @@
+use std::ops::Range;
+
+use iced_x86::FlowControl;
+use serde::{Deserialize, Serialize};
+
+use super::{assembly::AssemblyLine, notification::NotificationLevel, selection::parse_bytes, App};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature
+{
+    // None is a wildcard byte
+    pub bytes: Vec<Option<u8>>,
+}
+
+impl Signature
+{
+    pub fn parse(signature: &str) -> Result<Self, String>
+    {
+        let bytes = signature.split_whitespace().map(|token| match token
+        {
+            "?" | "??" => Ok(None),
+            token => u8::from_str_radix(token, 16).map(Some).map_err(|_| format!("Invalid signature byte: {}", token)),
+        }).collect::<Result<Vec<_>, String>>()?;
+        if bytes.first().is_none_or(|byte| byte.is_none())
+        {
+            return Err(format!("A signature must start with a byte: \"{}\"", signature));
+        }
+        Ok(Self { bytes })
+    }
+
+    pub fn matches_at(&self, data: &[u8], offset: usize) -> bool
+    {
+        data.len() >= offset + self.bytes.len() &&
+            self.bytes.iter().zip(&data[offset..]).all(|(expected, byte)| expected.is_none_or(|expected| expected == *byte))
+    }
+
+    pub fn find_all(&self, data: &[u8], range: Range<usize>) -> Vec<usize>
+    {
+        let first = self.bytes[0].expect("A signature starts with a byte");
+        range.filter(|offset| data.get(*offset) == Some(&first) && self.matches_at(data, *offset)).collect()
+    }
+}
+
+impl std::fmt::Display for Signature
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        let bytes: Vec<String> = self.bytes.iter().map(|byte| match byte
+        {
+            Some(byte) => format!("{:02X}", byte),
+            None => "??".to_string(),
+        }).collect();
+        write!(f, "{}", bytes.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignaturePatch
+{
+    #[serde(default)]
+    pub name: String,
+    pub signature: String,
+    // distance of the patch from the start of the match
+    #[serde(default)]
+    pub offset: i64,
+    // restricts the search to the function starting at this symbol
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub bytes: Option<String>,
+    #[serde(default)]
+    pub assembly: Option<String>,
+}
+
+impl <'a> App<'a>
+{
+    fn get_symbol_range(&self, symbol: &str) -> Result<Range<usize>, String>
+    {
+        let address = self.header.symbol_to_address(symbol).ok_or(format!("Symbol not found: {}", symbol))?;
+        let start = self.header.virtual_to_physical_address(address).ok_or(format!("Symbol {} is not in the file", symbol))? as usize;
+        let next = self.header.get_symbols()
+            .and_then(|symbols| symbols.keys().filter(|other| **other > address).min().cloned())
+            .and_then(|next| self.header.virtual_to_physical_address(next))
+            .map(|next| next as usize)
+            .filter(|next| *next > start)
+            .unwrap_or(self.data.len());
+        Ok(start..next.min(self.data.len()))
+    }
+
+    // returns the file offset and the bytes of a patch, it fails unless the signature matches exactly once
+    fn resolve_signature_patch(&self, patch: &SignaturePatch) -> Result<(usize, Vec<u8>), String>
+    {
+        let signature = Signature::parse(&patch.signature)?;
+        let range = match &patch.symbol
+        {
+            Some(symbol) => self.get_symbol_range(symbol)?,
+            None => 0..self.data.len(),
+        };
+        let matches = signature.find_all(&self.data, range);
+        let start = match matches.as_slice()
+        {
+            [start] => *start,
+            [] => return Err("the signature was not found".to_string()),
+            matches => return Err(format!("the signature matches {} times", matches.len())),
+        };
+        let offset = start as i64 + patch.offset;
+        if offset < 0 || offset as usize >= self.data.len()
+        {
+            return Err(format!("the patch offset {:#X} is outside the file", offset));
+        }
+        let offset = offset as usize;
+        let bytes = match (&patch.bytes, &patch.assembly)
+        {
+            (Some(bytes), None) => parse_bytes(bytes)?,
+            (None, Some(assembly)) =>
+            {
+                let address = self.header.physical_to_virtual_address(offset as u64).unwrap_or(offset as u64);
+                self.bytes_from_assembly(assembly, address)?
+            },
+            _ => return Err("exactly one of bytes and assembly must be set".to_string()),
+        };
+        if offset + bytes.len() > self.data.len()
+        {
+            return Err("the patch does not fit in the file".to_string());
+        }
+        Ok((offset, bytes))
+    }
+
+    // the patches are applied only if all of them can be resolved
+    pub(super) fn apply_signature_patches(&mut self, path: &str) -> Result<(), String>
+    {
+        let path = path.trim();
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let patches: Vec<SignaturePatch> = serde_json::from_str(&text).map_err(|e| format!("Invalid signature patches in {}: {}", path, e))?;
+        let mut resolved = Vec::new();
+        let mut errors = 0;
+        for (i, patch) in patches.iter().enumerate()
+        {
+            let name = if patch.name.is_empty() { format!("#{}", i) } else { patch.name.clone() };
+            match self.resolve_signature_patch(patch)
+            {
+                Ok((offset, bytes)) => resolved.push((name, offset, bytes)),
+                Err(e) =>
+                {
+                    self.log(NotificationLevel::Error, &format!("Patch {}: {}", name, e));
+                    errors += 1;
+                }
+            }
+        }
+        if errors > 0
+        {
+            return Err(format!("{} of {} signature patches failed, no changes were applied", errors, patches.len()));
+        }
+        for (name, offset, bytes) in resolved
+        {
+            self.jump_to(offset, false);
+            self.write_bytes(offset, &bytes);
+            self.log(NotificationLevel::Info, &format!("Patch {}: wrote {} bytes at {:#X}", name, bytes.len(), offset));
+        }
+        Ok(())
+    }
+
+    pub(super) fn find_signature(&mut self, signature: &str) -> Result<(), String>
+    {
+        let signature = Signature::parse(signature)?;
+        let matches = signature.find_all(&self.data, 0..self.data.len());
+        let first = *matches.first().ok_or("The signature was not found")?;
+        self.log(NotificationLevel::Info, &format!("The signature matches {} times, the first match is at {:#X}", matches.len(), first));
+        self.jump_to(first, false);
+        Ok(())
+    }
+
+    // operand bytes that change between builds, such as addresses and branch displacements, are wildcarded
+    fn get_instruction_signature(&self, offset: usize) -> Option<(Vec<Option<u8>>, usize)>
+    {
+        let address = self.header.physical_to_virtual_address(offset as u64).unwrap_or(offset as u64);
+        let mut decoder = iced_x86::Decoder::new(self.header.bitness(), &self.data[offset..], iced_x86::DecoderOptions::NONE);
+        decoder.set_ip(address);
+        let instruction = decoder.decode();
+        if instruction.is_invalid()
+        {
+            return None;
+        }
+        let constant_offsets = decoder.get_constant_offsets(&instruction);
+        let mut bytes: Vec<Option<u8>> = self.data[offset..offset + instruction.len()].iter().map(|byte| Some(*byte)).collect();
+        let is_branch = matches!(instruction.flow_control(), FlowControl::Call | FlowControl::UnconditionalBranch | FlowControl::ConditionalBranch | FlowControl::XbeginXabortXend);
+        let mut wildcard = |start: usize, size: usize| bytes[start..start + size].iter_mut().for_each(|byte| *byte = None);
+        if constant_offsets.has_displacement() && (instruction.is_ip_rel_memory_operand() || constant_offsets.displacement_size() >= 4)
+        {
+            wildcard(constant_offsets.displacement_offset(), constant_offsets.displacement_size());
+        }
+        if constant_offsets.has_immediate() && (is_branch || constant_offsets.immediate_size() >= 4)
+        {
+            wildcard(constant_offsets.immediate_offset(), constant_offsets.immediate_size());
+        }
+        if constant_offsets.has_immediate2() && constant_offsets.immediate_size2() >= 4
+        {
+            wildcard(constant_offsets.immediate_offset2(), constant_offsets.immediate_size2());
+        }
+        Some((bytes, instruction.len()))
+    }
+
+    // following instructions are added until the signature is unique
+    pub(super) fn generate_signature_at_cursor(&mut self) -> Result<(), String>
+    {
+        let start = match self.get_current_instruction()
+        {
+            AssemblyLine::Instruction(instruction) => instruction.file_address as usize,
+            AssemblyLine::Data(_) | AssemblyLine::SectionTag(_) => return Err("There is no instruction at the cursor".to_string()),
+        };
+        let mut signature = Signature { bytes: Vec::new() };
+        let mut matches = 0;
+        while signature.bytes.len() < 64
+        {
+            let (bytes, size) = match self.get_instruction_signature(start + signature.bytes.len())
+            {
+                Some(instruction) => instruction,
+                None => break,
+            };
+            signature.bytes.extend(bytes);
+            matches = signature.find_all(&self.data, 0..self.data.len()).len();
+            if matches == 1 || start + signature.bytes.len() + size > self.data.len()
+            {
+                break;
+            }
+        }
+        if signature.bytes.is_empty()
+        {
+            return Err("Could not decode the instruction at the cursor".to_string());
+        }
+        if matches != 1
+        {
+            self.log(NotificationLevel::Warning, &format!("The signature is not unique, it matches {} times", matches));
+        }
+        self.copy_to_clipboard(signature.to_string(), "signature");
+        Ok(())
+    }
+}
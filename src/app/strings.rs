@@ -0,0 +1,217 @@
+use ratatui::text::{Line, Span};
+
+use crate::{fuzzer::fuzzer::Fuzzer, headers::elf::Endianness};
+
+use super::{color_settings::ColorSettings, notification::NotificationLevel, App};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding
+{
+    Ascii,
+    Utf8,
+    Utf16,
+}
+
+impl StringEncoding
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            StringEncoding::Ascii => "ascii",
+            StringEncoding::Utf8 => "utf8",
+            StringEncoding::Utf16 => "utf16",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundString
+{
+    pub offset: usize,
+    pub size: usize,
+    pub encoding: StringEncoding,
+    pub text: String,
+}
+
+fn is_printable(c: char) -> bool
+{
+    c == '\t' || !c.is_control()
+}
+
+fn push_string(strings: &mut Vec<FoundString>, offset: usize, size: usize, encoding: StringEncoding, text: &mut String, length: usize, min_length: usize)
+{
+    if length >= min_length
+    {
+        strings.push(FoundString { offset, size, encoding, text: std::mem::take(text) });
+    }
+    else
+    {
+        text.clear();
+    }
+}
+
+fn find_utf8_strings(data: &[u8], min_length: usize, strings: &mut Vec<FoundString>)
+{
+    let mut start = 0;
+    let mut text = String::new();
+    let mut length = 0;
+    let mut i = 0;
+    while i < data.len()
+    {
+        let char_len = match data[i]
+        {
+            0x00..=0x7F => 1,
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => 0,
+        };
+        let c = if char_len > 0 && i + char_len <= data.len()
+        {
+            std::str::from_utf8(&data[i..i + char_len]).ok().and_then(|s| s.chars().next()).filter(|c| is_printable(*c))
+        }
+        else
+        {
+            None
+        };
+        match c
+        {
+            Some(c) =>
+            {
+                if length == 0
+                {
+                    start = i;
+                }
+                text.push(c);
+                length += 1;
+                i += char_len;
+            },
+            None =>
+            {
+                let encoding = if text.is_ascii() { StringEncoding::Ascii } else { StringEncoding::Utf8 };
+                push_string(strings, start, i - start, encoding, &mut text, length, min_length);
+                length = 0;
+                i += 1;
+            }
+        }
+    }
+    let encoding = if text.is_ascii() { StringEncoding::Ascii } else { StringEncoding::Utf8 };
+    push_string(strings, start, data.len() - start, encoding, &mut text, length, min_length);
+}
+
+fn find_utf16_strings(data: &[u8], min_length: usize, endianness: Endianness, strings: &mut Vec<FoundString>)
+{
+    for alignment in 0..2
+    {
+        let units = data[alignment.min(data.len())..].chunks_exact(2).map(|unit| match endianness
+        {
+            Endianness::Little => u16::from_le_bytes([unit[0], unit[1]]),
+            Endianness::Big => u16::from_be_bytes([unit[0], unit[1]]),
+        });
+        let mut start = 0;
+        let mut text = String::new();
+        let mut length = 0;
+        let mut position = alignment;
+        for c in char::decode_utf16(units)
+        {
+            let size = c.as_ref().map(|c| c.len_utf16() * 2).unwrap_or(2);
+            // pairs of ASCII characters decode to valid CJK characters, so only
+            // alphabetic scripts are accepted to avoid reporting them as UTF-16
+            match c.ok().filter(|c| is_printable(*c) && (*c as u32) < 0x800)
+            {
+                Some(c) =>
+                {
+                    if length == 0
+                    {
+                        start = position;
+                    }
+                    text.push(c);
+                    length += 1;
+                },
+                _ =>
+                {
+                    push_string(strings, start, position - start, StringEncoding::Utf16, &mut text, length, min_length);
+                    length = 0;
+                }
+            }
+            position += size;
+        }
+        push_string(strings, start, position - start, StringEncoding::Utf16, &mut text, length, min_length);
+    }
+}
+
+pub fn find_strings(data: &[u8], min_length: usize, endianness: Endianness) -> Vec<FoundString>
+{
+    let min_length = min_length.max(1);
+    let mut strings = Vec::new();
+    find_utf8_strings(data, min_length, &mut strings);
+    find_utf16_strings(data, min_length, endianness, &mut strings);
+    strings.sort_by_key(|s| s.offset);
+    strings
+}
+
+impl <'a> App<'a>
+{
+    pub(super) fn update_strings(&mut self)
+    {
+        self.strings = find_strings(&self.data, self.strings_min_length, self.header.endianness());
+        let texts: Vec<&str> = self.strings.iter().map(|s| s.text.as_str()).collect();
+        self.strings_fuzzer = Fuzzer::new(&texts);
+        self.log(NotificationLevel::Debug, &format!("Found {} strings with at least {} characters", self.strings.len(), self.strings_min_length));
+    }
+
+    pub(super) fn find_strings(&self, filter: &str) -> Vec<usize>
+    {
+        if filter.is_empty()
+        {
+            (0..self.strings.len()).collect()
+        }
+        else
+        {
+            self.strings_fuzzer.fuzzy_filter(filter)
+        }
+    }
+
+    pub(super) fn jump_to_string(&mut self, strings: &[usize], scroll: usize)
+    {
+        if let Some(string) = strings.get(scroll).and_then(|i| self.strings.get(*i))
+        {
+            let offset = string.offset;
+            self.log(NotificationLevel::Debug, &format!("Jumping to string at {:#X}", offset));
+            self.jump_to(offset, false);
+        }
+        else
+        {
+            self.log(NotificationLevel::Error, "No strings matching the search pattern found");
+        }
+    }
+
+    pub(super) fn string_to_line(&self, color_settings: &ColorSettings, string: &FoundString, selected: bool, width: usize) -> Line<'a>
+    {
+        let section = self.header.get_sections().into_iter()
+            .find(|s| string.offset as u64 >= s.address && (string.offset as u64) < s.address + s.size);
+        let (section_name, address) = match section
+        {
+            Some(section) => (section.name.clone(), format!("{:X}", section.virtual_address + string.offset as u64 - section.address)),
+            None => (String::new(), format!("{:X}", string.offset)),
+        };
+        let text_width = width.saturating_sub(34);
+        let text: String = string.text.chars().map(|c| if c == '\t' { ' ' } else { c }).take(text_width).collect();
+        let padding = text_width.saturating_sub(text.chars().count());
+        let (style_text, style_info, style_address) = if selected
+        {
+            (color_settings.assembly_selected, color_settings.assembly_selected, color_settings.assembly_selected)
+        }
+        else
+        {
+            (color_settings.hex_alphanumeric, color_settings.assembly_section, color_settings.assembly_address)
+        };
+        Line::from(vec![
+            Span::styled(text, style_text),
+            Span::styled(" ".repeat(padding + 1), style_text),
+            Span::styled(format!("{:5} {:>8.8}", string.encoding.name(), section_name), style_info),
+            Span::styled(format!(" {:>16}", address), style_address),
+        ]).left_aligned()
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use crate::{asm::assembler::sanitize_symbol, headers::header::Header};
+
+use super::{editor::{is_mnemonic, is_register, is_word_char}, notification::NotificationLevel, App};
+
+// a label is defined by the whole token followed by a colon, loop_a: does not define a
+fn defines_label(assembly: &str, token: &str) -> bool
+{
+    assembly.match_indices(token).any(|(index, _)|
+        !assembly[..index].ends_with(is_word_char) && assembly[index + token.len()..].trim_start_matches([' ', '\t']).starts_with(':'))
+}
+
+impl <'a> App<'a>
+{
+    // symbols and labels referenced by a patch, loc_<address> refers to any virtual address
+    pub(super) fn get_assembly_symbols(assembly: &str, header: &Header, labels: &HashMap<u64, String>) -> Vec<(String, u64)>
+    {
+        let symbols = header.get_symbols().unwrap_or_default();
+        let mut sorted_symbols: Vec<(u64, &String)> = symbols.iter().filter(|(address, _)| **address != 0).map(|(address, name)| (*address, name)).collect();
+        sorted_symbols.sort();
+        let mut sorted_labels: Vec<(u64, &String)> = labels.iter().map(|(address, name)| (*address, name)).collect();
+        sorted_labels.sort();
+        let mut names: HashMap<String, u64> = HashMap::new();
+        // labels take precedence over symbols with the same name
+        for (address, name) in sorted_labels.into_iter().chain(sorted_symbols)
+        {
+            names.entry(sanitize_symbol(name)).or_insert(address);
+        }
+        let mut referenced: Vec<(String, u64)> = Vec::new();
+        for token in assembly.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).filter(|token| !token.is_empty())
+        {
+            // labels defined in the patch hide the symbols, mnemonics and registers are never symbols
+            if referenced.iter().any(|(name, _)| name == token) || defines_label(assembly, token) || is_mnemonic(token) || is_register(token)
+            {
+                continue;
+            }
+            let address = names.get(token).copied()
+                .or_else(|| token.strip_prefix("loc_").and_then(|address| u64::from_str_radix(address, 16).ok()));
+            if let Some(address) = address
+            {
+                referenced.push((token.to_string(), address));
+            }
+        }
+        referenced
+    }
+
+    pub(super) fn get_symbol_at(&self, address: u64) -> String
+    {
+        self.header.get_symbols().and_then(|symbols| symbols.get(&address).cloned()).unwrap_or_default()
+    }
+
+    pub(super) fn get_fuzzy_symbol(&self, filter: &str, symbols: &[(u64, String)], scroll: usize) -> Option<(u64, String)>
+    {
+        if filter.is_empty()
+        {
+            self.header.get_symbols()?.iter().nth(scroll).map(|(address, name)| (*address, name.clone()))
+        }
+        else
+        {
+            symbols.get(scroll).cloned()
+        }
+    }
+
+    pub(super) fn set_symbol(&mut self, address: u64, name: &str) -> Result<(), String>
+    {
+        let name = name.trim();
+        if name.is_empty()
+        {
+            self.remove_symbol(address);
+            return Ok(());
+        }
+        let old_name = self.get_symbol_at(address);
+        self.header.set_symbol(address, name)?;
+        if old_name.is_empty()
+        {
+            self.log(NotificationLevel::Info, &format!("Added symbol {} at {:#X}", name, address));
+        }
+        else
+        {
+            self.log(NotificationLevel::Info, &format!("Renamed symbol {} to {}", old_name, name));
+        }
+        if self.write_symbols && !self.header.is_symbol_writable(address)
+        {
+            self.log(NotificationLevel::Warning, &format!("Symbol {} is not in the file symbol table, it will not be saved", name));
+        }
+        Ok(())
+    }
+
+    pub(super) fn remove_symbol(&mut self, address: u64)
+    {
+        if let Some(name) = self.header.remove_symbol(address)
+        {
+            self.log(NotificationLevel::Info, &format!("Removed symbol {} at {:#X}", name, address));
+        }
+    }
+
+    // symbols added, renamed (Some) or removed (None) in memory and not written to the file yet
+    pub(super) fn get_symbol_edits(&self) -> Vec<(u64, Option<String>)>
+    {
+        let edited = self.header.get_symbols().unwrap_or_default();
+        let original = Header::parse_header(&self.data).get_symbols().unwrap_or_default();
+        let mut edits: Vec<(u64, Option<String>)> = edited.iter()
+            .filter(|(address, name)| original.get(address) != Some(name))
+            .map(|(address, name)| (*address, Some(name.clone())))
+            .collect();
+        edits.extend(original.keys().filter(|address| !edited.contains_key(address)).map(|address| (*address, None)));
+        edits
+    }
+
+    // the edits are carried over when the header is parsed again
+    pub(super) fn apply_symbol_edits(&mut self, edits: Vec<(u64, Option<String>)>)
+    {
+        for (address, name) in edits
+        {
+            match name
+            {
+                Some(name) =>
+                {
+                    if let Err(e) = self.header.set_symbol(address, &name)
+                    {
+                        self.log(NotificationLevel::Warning, &format!("Symbol {} at {:#X} is lost: {}", name, address, e));
+                    }
+                },
+                None =>
+                {
+                    self.header.remove_symbol(address);
+                },
+            }
+        }
+    }
+
+    pub(super) fn write_symbols_to_data(&mut self)
+    {
+        let (patches, errors) = self.header.symbol_patches();
+        for error in errors
+        {
+            self.log(NotificationLevel::Warning, &error);
+        }
+        for (offset, bytes) in patches.iter()
+        {
+            self.write_bytes(*offset as usize, bytes);
+        }
+        if !patches.is_empty()
+        {
+            self.log(NotificationLevel::Info, &format!("Wrote {} symbols to the symbol table", patches.len()));
+        }
+    }
+}
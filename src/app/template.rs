@@ -0,0 +1,822 @@
+use std::collections::HashMap;
+
+use ratatui::text::{Line, Span, Text};
+
+use crate::headers::{elf::Endianness, header::Header};
+
+use super::{color_settings::ColorSettings, inspector::{InspectorField, InspectorType}, notification::NotificationLevel, App};
+
+const MAX_TEMPLATE_FIELDS: usize = 16384;
+const MAX_TEMPLATE_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveType
+{
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    Char,
+}
+
+impl PrimitiveType
+{
+    pub fn from_name(name: &str) -> Option<Self>
+    {
+        match name
+        {
+            "u8" | "uint8_t" | "byte" => Some(PrimitiveType::U8),
+            "u16" | "uint16_t" => Some(PrimitiveType::U16),
+            "u32" | "uint32_t" => Some(PrimitiveType::U32),
+            "u64" | "uint64_t" => Some(PrimitiveType::U64),
+            "i8" | "int8_t" => Some(PrimitiveType::I8),
+            "i16" | "int16_t" => Some(PrimitiveType::I16),
+            "i32" | "int32_t" => Some(PrimitiveType::I32),
+            "i64" | "int64_t" => Some(PrimitiveType::I64),
+            "f32" | "float" => Some(PrimitiveType::F32),
+            "f64" | "double" => Some(PrimitiveType::F64),
+            "char" => Some(PrimitiveType::Char),
+            _ => None,
+        }
+    }
+
+    pub fn inspector_field(&self, endianness: Endianness) -> InspectorField
+    {
+        let data_type = match self
+        {
+            PrimitiveType::U8 | PrimitiveType::Char => InspectorType::U8,
+            PrimitiveType::U16 => InspectorType::U16,
+            PrimitiveType::U32 => InspectorType::U32,
+            PrimitiveType::U64 => InspectorType::U64,
+            PrimitiveType::I8 => InspectorType::I8,
+            PrimitiveType::I16 => InspectorType::I16,
+            PrimitiveType::I32 => InspectorType::I32,
+            PrimitiveType::I64 => InspectorType::I64,
+            PrimitiveType::F32 => InspectorType::F32,
+            PrimitiveType::F64 => InspectorType::F64,
+        };
+        InspectorField::new(data_type, endianness)
+    }
+
+    pub fn size(&self) -> usize
+    {
+        match self
+        {
+            PrimitiveType::U8 | PrimitiveType::I8 | PrimitiveType::Char => 1,
+            PrimitiveType::U16 | PrimitiveType::I16 => 2,
+            PrimitiveType::U32 | PrimitiveType::I32 | PrimitiveType::F32 => 4,
+            PrimitiveType::U64 | PrimitiveType::I64 | PrimitiveType::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArrayLength
+{
+    Fixed(usize),
+    Field(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDefinition
+{
+    pub name: String,
+    pub type_name: String,
+    pub endianness: Option<Endianness>,
+    pub array_length: Option<ArrayLength>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructDefinition
+{
+    pub name: String,
+    pub endianness: Option<Endianness>,
+    pub fields: Vec<FieldDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumDefinition
+{
+    pub name: String,
+    pub base: PrimitiveType,
+    pub variants: Vec<(String, i128)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldEditor
+{
+    Primitive(InspectorField),
+    Char,
+    Enum(InspectorField, Vec<(String, i128)>),
+    String(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateField
+{
+    pub name: String,
+    pub type_name: String,
+    pub depth: usize,
+    pub offset: usize,
+    pub size: usize,
+    pub value: Option<String>,
+    pub editor: Option<FieldEditor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template
+{
+    pub structs: Vec<StructDefinition>,
+    pub enums: Vec<EnumDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedTemplate
+{
+    pub template: Template,
+    pub offset: usize,
+    pub fields: Vec<TemplateField>,
+    pub leaves: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token
+{
+    Identifier(String),
+    Number(i128),
+    Symbol(char),
+}
+
+struct Parser
+{
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser
+{
+    fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String>
+    {
+        let mut tokens = Vec::new();
+        let chars: Vec<char> = source.chars().collect();
+        let mut line = 1;
+        let mut i = 0;
+        while i < chars.len()
+        {
+            let c = chars[i];
+            if c == '\n'
+            {
+                line += 1;
+                i += 1;
+            }
+            else if c.is_whitespace()
+            {
+                i += 1;
+            }
+            else if c == '/' && chars.get(i + 1) == Some(&'/')
+            {
+                while i < chars.len() && chars[i] != '\n'
+                {
+                    i += 1;
+                }
+            }
+            else if c == '/' && chars.get(i + 1) == Some(&'*')
+            {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/'))
+                {
+                    if chars[i] == '\n'
+                    {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                i += 2;
+            }
+            else if c.is_ascii_alphabetic() || c == '_'
+            {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_')
+                {
+                    i += 1;
+                }
+                tokens.push((Token::Identifier(chars[start..i].iter().collect()), line));
+            }
+            else if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()))
+            {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_alphanumeric()
+                {
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().collect();
+                let (negative, digits) = match literal.strip_prefix('-')
+                {
+                    Some(digits) => (true, digits),
+                    None => (false, literal.as_str()),
+                };
+                let number = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X"))
+                {
+                    i128::from_str_radix(hex, 16)
+                }
+                else
+                {
+                    digits.parse::<i128>()
+                }.map_err(|_| format!("line {}: invalid number \"{}\"", line, literal))?;
+                tokens.push((Token::Number(if negative { -number } else { number }), line));
+            }
+            else if "{}[];,=:".contains(c)
+            {
+                tokens.push((Token::Symbol(c), line));
+                i += 1;
+            }
+            else
+            {
+                return Err(format!("line {}: unexpected character '{}'", line, c));
+            }
+        }
+        Ok(tokens)
+    }
+
+    fn peek(&self) -> Option<&Token>
+    {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize
+    {
+        self.tokens.get(self.position).or(self.tokens.last()).map(|(_, line)| *line).unwrap_or(1)
+    }
+
+    fn next(&mut self) -> Option<Token>
+    {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn is_symbol(&self, symbol: char) -> bool
+    {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), String>
+    {
+        if self.is_symbol(symbol)
+        {
+            self.position += 1;
+            Ok(())
+        }
+        else
+        {
+            Err(format!("line {}: expected '{}'", self.line(), symbol))
+        }
+    }
+
+    fn expect_identifier(&mut self) -> Result<String, String>
+    {
+        let line = self.line();
+        match self.next()
+        {
+            Some(Token::Identifier(identifier)) => Ok(identifier),
+            _ => Err(format!("line {}: expected an identifier", line)),
+        }
+    }
+
+    fn parse_endianness(&mut self) -> Option<Endianness>
+    {
+        let endianness = match self.peek()
+        {
+            Some(Token::Identifier(identifier)) if identifier == "big" || identifier == "be" => Some(Endianness::Big),
+            Some(Token::Identifier(identifier)) if identifier == "little" || identifier == "le" => Some(Endianness::Little),
+            _ => None,
+        };
+        if endianness.is_some()
+        {
+            self.position += 1;
+        }
+        endianness
+    }
+
+    fn parse_enum(&mut self) -> Result<EnumDefinition, String>
+    {
+        let name = self.expect_identifier()?;
+        let base = if self.is_symbol(':')
+        {
+            self.position += 1;
+            let line = self.line();
+            let base_name = self.expect_identifier()?;
+            match PrimitiveType::from_name(&base_name)
+            {
+                Some(PrimitiveType::F32) | Some(PrimitiveType::F64) | None =>
+                    return Err(format!("line {}: invalid enum base type \"{}\"", line, base_name)),
+                Some(base) => base,
+            }
+        }
+        else
+        {
+            PrimitiveType::U32
+        };
+        self.expect_symbol('{')?;
+        let mut variants = Vec::new();
+        let mut next_value = 0;
+        while !self.is_symbol('}')
+        {
+            let variant = self.expect_identifier()?;
+            if self.is_symbol('=')
+            {
+                self.position += 1;
+                let line = self.line();
+                match self.next()
+                {
+                    Some(Token::Number(value)) => next_value = value,
+                    _ => return Err(format!("line {}: expected a number", line)),
+                }
+            }
+            variants.push((variant, next_value));
+            next_value += 1;
+            if !self.is_symbol('}')
+            {
+                self.expect_symbol(',')?;
+            }
+        }
+        self.expect_symbol('}')?;
+        if self.is_symbol(';')
+        {
+            self.position += 1;
+        }
+        Ok(EnumDefinition { name, base, variants })
+    }
+
+    fn parse_struct(&mut self, endianness: Option<Endianness>) -> Result<StructDefinition, String>
+    {
+        let name = self.expect_identifier()?;
+        self.expect_symbol('{')?;
+        let mut fields = Vec::new();
+        while !self.is_symbol('}')
+        {
+            if self.peek().is_none()
+            {
+                return Err(format!("line {}: unterminated struct \"{}\"", self.line(), name));
+            }
+            let field_endianness = self.parse_endianness();
+            let type_name = self.expect_identifier()?;
+            let field_name = self.expect_identifier()?;
+            let array_length = if self.is_symbol('[')
+            {
+                self.position += 1;
+                let line = self.line();
+                let length = match self.next()
+                {
+                    Some(Token::Number(length)) if length >= 0 => ArrayLength::Fixed(length as usize),
+                    Some(Token::Identifier(field)) => ArrayLength::Field(field),
+                    _ => return Err(format!("line {}: expected an array length", line)),
+                };
+                self.expect_symbol(']')?;
+                Some(length)
+            }
+            else
+            {
+                None
+            };
+            self.expect_symbol(';')?;
+            fields.push(FieldDefinition { name: field_name, type_name, endianness: field_endianness, array_length });
+        }
+        self.expect_symbol('}')?;
+        if self.is_symbol(';')
+        {
+            self.position += 1;
+        }
+        Ok(StructDefinition { name, endianness, fields })
+    }
+}
+
+impl Template
+{
+    pub fn parse(source: &str) -> Result<Template, String>
+    {
+        let mut parser = Parser { tokens: Parser::tokenize(source)?, position: 0 };
+        let mut template = Template { structs: Vec::new(), enums: Vec::new() };
+        while parser.peek().is_some()
+        {
+            let endianness = parser.parse_endianness();
+            let line = parser.line();
+            match parser.next()
+            {
+                Some(Token::Identifier(keyword)) if keyword == "struct" => template.structs.push(parser.parse_struct(endianness)?),
+                Some(Token::Identifier(keyword)) if keyword == "enum" && endianness.is_none() => template.enums.push(parser.parse_enum()?),
+                _ => return Err(format!("line {}: expected \"struct\" or \"enum\"", line)),
+            }
+        }
+        if template.structs.is_empty()
+        {
+            return Err("The template does not define any struct".to_string());
+        }
+        Ok(template)
+    }
+
+    // the last struct in the file is the one applied at the cursor
+    pub fn root(&self) -> &StructDefinition
+    {
+        self.structs.last().expect("Templates always contain at least one struct")
+    }
+
+    pub fn apply(&self, data: &[u8], offset: usize, header: &Header) -> Result<Vec<TemplateField>, String>
+    {
+        let mut fields = Vec::new();
+        let root = self.root();
+        self.apply_struct(root, &root.name, &root.name, data, offset, 0, header.endianness(), header, &mut fields)?;
+        Ok(fields)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn apply_struct(&self, definition: &StructDefinition, name: &str, type_name: &str, data: &[u8], offset: usize, depth: usize, endianness: Endianness, header: &Header, fields: &mut Vec<TemplateField>) -> Result<usize, String>
+    {
+        if depth > MAX_TEMPLATE_DEPTH
+        {
+            return Err(format!("Struct \"{}\" is nested too deeply", definition.name));
+        }
+        let endianness = definition.endianness.unwrap_or(endianness);
+        let struct_index = fields.len();
+        fields.push(TemplateField {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            depth,
+            offset,
+            size: 0,
+            value: None,
+            editor: None,
+        });
+        let mut siblings: HashMap<String, i128> = HashMap::new();
+        let mut current_offset = offset;
+        for field in &definition.fields
+        {
+            let field_endianness = field.endianness.unwrap_or(endianness);
+            match &field.array_length
+            {
+                None =>
+                {
+                    let (size, value) = self.apply_type(&field.type_name, &field.name, &field.type_name, data, current_offset, depth + 1, field_endianness, header, fields)?;
+                    if let Some(value) = value
+                    {
+                        siblings.insert(field.name.clone(), value);
+                    }
+                    current_offset += size;
+                },
+                Some(length) =>
+                {
+                    let length = match length
+                    {
+                        ArrayLength::Fixed(length) => *length,
+                        ArrayLength::Field(name) =>
+                        {
+                            let length = *siblings.get(name)
+                                .ok_or(format!("Array length \"{}\" is not an integer field of \"{}\"", name, definition.name))?;
+                            if length < 0
+                            {
+                                return Err(format!("Negative array length for \"{}\"", field.name));
+                            }
+                            length as usize
+                        }
+                    };
+                    let array_type_name = format!("{}[{}]", field.type_name, length);
+                    if field.type_name == "char"
+                    {
+                        let end = current_offset.checked_add(length).filter(|end| *end <= data.len())
+                            .ok_or(format!("Field \"{}\" goes past the end of the file", field.name))?;
+                        let string = data[current_offset..end].iter()
+                            .take_while(|c| **c != 0)
+                            .map(|c| c.escape_ascii().to_string())
+                            .collect::<String>();
+                        fields.push(TemplateField {
+                            name: field.name.clone(),
+                            type_name: array_type_name,
+                            depth: depth + 1,
+                            offset: current_offset,
+                            size: length,
+                            value: Some(format!("\"{}\"", string)),
+                            editor: Some(FieldEditor::String(length)),
+                        });
+                        current_offset = end;
+                    }
+                    else
+                    {
+                        let array_index = fields.len();
+                        fields.push(TemplateField {
+                            name: field.name.clone(),
+                            type_name: array_type_name,
+                            depth: depth + 1,
+                            offset: current_offset,
+                            size: 0,
+                            value: None,
+                            editor: None,
+                        });
+                        let array_start = current_offset;
+                        for i in 0..length
+                        {
+                            // every element adds at least one field, a long array must not fill the memory before the check below
+                            if fields.len() > MAX_TEMPLATE_FIELDS
+                            {
+                                return Err(format!("The template has more than {} fields", MAX_TEMPLATE_FIELDS));
+                            }
+                            let element_name = format!("{}[{}]", field.name, i);
+                            let (size, _) = self.apply_type(&field.type_name, &element_name, &field.type_name, data, current_offset, depth + 2, field_endianness, header, fields)?;
+                            current_offset += size;
+                        }
+                        fields[array_index].size = current_offset - array_start;
+                    }
+                }
+            }
+            if fields.len() > MAX_TEMPLATE_FIELDS
+            {
+                return Err(format!("The template has more than {} fields", MAX_TEMPLATE_FIELDS));
+            }
+        }
+        fields[struct_index].size = current_offset - offset;
+        Ok(current_offset - offset)
+    }
+
+    // returns the size of the field and its value if it is an integer
+    #[allow(clippy::too_many_arguments)]
+    fn apply_type(&self, type_name: &str, name: &str, display_type_name: &str, data: &[u8], offset: usize, depth: usize, endianness: Endianness, header: &Header, fields: &mut Vec<TemplateField>) -> Result<(usize, Option<i128>), String>
+    {
+        if let Some(primitive) = PrimitiveType::from_name(type_name)
+        {
+            let inspector_field = primitive.inspector_field(endianness);
+            let bytes = data.get(offset..).unwrap_or_default();
+            let value = inspector_field.decode(bytes, header)
+                .ok_or(format!("Field \"{}\" goes past the end of the file", name))?;
+            let integer = inspector_field.decode_integer(bytes);
+            let (value, editor) = if primitive == PrimitiveType::Char
+            {
+                (format!("'{}'", bytes[0].escape_ascii()), FieldEditor::Char)
+            }
+            else
+            {
+                (value, FieldEditor::Primitive(inspector_field))
+            };
+            fields.push(TemplateField {
+                name: name.to_string(),
+                type_name: display_type_name.to_string(),
+                depth,
+                offset,
+                size: primitive.size(),
+                value: Some(value),
+                editor: Some(editor),
+            });
+            Ok((primitive.size(), integer))
+        }
+        else if let Some(enum_definition) = self.enums.iter().find(|e| e.name == type_name)
+        {
+            let inspector_field = enum_definition.base.inspector_field(endianness);
+            let integer = inspector_field.decode_integer(data.get(offset..).unwrap_or_default())
+                .ok_or(format!("Field \"{}\" goes past the end of the file", name))?;
+            let value = match enum_definition.variants.iter().find(|(_, value)| *value == integer)
+            {
+                Some((variant, _)) => format!("{} ({})", variant, integer),
+                None => format!("{} (unknown)", integer),
+            };
+            fields.push(TemplateField {
+                name: name.to_string(),
+                type_name: display_type_name.to_string(),
+                depth,
+                offset,
+                size: enum_definition.base.size(),
+                value: Some(value),
+                editor: Some(FieldEditor::Enum(inspector_field, enum_definition.variants.clone())),
+            });
+            Ok((enum_definition.base.size(), Some(integer)))
+        }
+        else if let Some(struct_definition) = self.structs.iter().find(|s| s.name == type_name)
+        {
+            let size = self.apply_struct(struct_definition, name, display_type_name, data, offset, depth, endianness, header, fields)?;
+            Ok((size, None))
+        }
+        else
+        {
+            Err(format!("Unknown type \"{}\"", type_name))
+        }
+    }
+}
+
+impl FieldEditor
+{
+    pub fn encode(&self, value: &str, header: &Header) -> Result<Vec<u8>, String>
+    {
+        match self
+        {
+            FieldEditor::Primitive(field) => field.encode(value, header),
+            FieldEditor::Char =>
+            {
+                let value = value.trim_matches('\'');
+                if value.len() != 1
+                {
+                    return Err(format!("Invalid char: {}", value));
+                }
+                Ok(value.as_bytes().to_vec())
+            },
+            FieldEditor::Enum(field, variants) =>
+            {
+                let name = value.split_whitespace().next().unwrap_or_default();
+                match variants.iter().find(|(variant, _)| variant == name)
+                {
+                    Some((_, value)) => field.encode(&value.to_string(), header),
+                    None => field.encode(value, header),
+                }
+            },
+            FieldEditor::String(length) =>
+            {
+                let value = value.trim_matches('"');
+                if value.len() > *length
+                {
+                    return Err(format!("The string is longer than {} bytes", length));
+                }
+                let mut bytes = value.as_bytes().to_vec();
+                bytes.resize(*length, 0);
+                Ok(bytes)
+            },
+        }
+    }
+}
+
+impl AppliedTemplate
+{
+    pub fn new(template: Template, offset: usize, data: &[u8], header: &Header) -> Result<Self, String>
+    {
+        let fields = template.apply(data, offset, header)?;
+        let leaves = fields.iter().enumerate()
+            .filter(|(_, field)| field.editor.is_some() && field.size > 0)
+            .map(|(i, _)| i)
+            .collect();
+        Ok(Self { template, offset, fields, leaves })
+    }
+
+    pub fn size(&self) -> usize
+    {
+        self.fields[0].size
+    }
+
+    // index in leaves of the field containing the byte
+    pub fn leaf_at(&self, index: usize) -> Option<usize>
+    {
+        let leaf = self.leaves.partition_point(|leaf| self.fields[*leaf].offset + self.fields[*leaf].size <= index);
+        if leaf < self.leaves.len() && self.fields[self.leaves[leaf]].offset <= index
+        {
+            Some(leaf)
+        }
+        else
+        {
+            None
+        }
+    }
+
+    // the deepest field containing the byte
+    pub fn field_at(&self, index: usize) -> Option<usize>
+    {
+        self.fields.iter().rposition(|field| index >= field.offset && index < field.offset + field.size)
+    }
+}
+
+impl <'a> App<'a>
+{
+    pub(super) fn load_template(&mut self, path: &str) -> Result<(), String>
+    {
+        let old_range = self.template.as_ref().map(|t| (t.offset, t.offset + t.size()));
+        if path.is_empty()
+        {
+            self.template = None;
+            self.log(NotificationLevel::Info, "Template removed");
+        }
+        else
+        {
+            let source = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            let template = Template::parse(&source)?;
+            let offset = self.get_cursor_position().global_byte_index;
+            let applied_template = AppliedTemplate::new(template, offset, &self.data, &self.header)?;
+            self.log(NotificationLevel::Info, &format!("Applied struct {} ({}B) at {:#X}", applied_template.template.root().name, applied_template.size(), offset));
+            self.template = Some(applied_template);
+        }
+        if let Some((from, to)) = old_range
+        {
+            self.restyle_bytes(from, to);
+        }
+        self.color_template_fields();
+        Ok(())
+    }
+
+    pub(super) fn reapply_template(&mut self) -> Result<(), String>
+    {
+        if let Some(template) = self.template.take()
+        {
+            let old_range = (template.offset, template.offset + template.size());
+            let applied_template = AppliedTemplate::new(template.template, template.offset, &self.data, &self.header);
+            self.restyle_bytes(old_range.0, old_range.1);
+            self.template = Some(applied_template?);
+            self.color_template_fields();
+        }
+        Ok(())
+    }
+
+    pub(super) fn color_template_fields(&mut self)
+    {
+        if let Some(template) = &self.template
+        {
+            let (from, to) = (template.offset, template.offset + template.size());
+            self.restyle_bytes(from, to);
+        }
+    }
+
+    pub(super) fn get_template_field_at_cursor(&self) -> Option<usize>
+    {
+        let template = self.template.as_ref()?;
+        let field = template.field_at(self.get_cursor_position().global_byte_index)?;
+        if template.fields[field].editor.is_some()
+        {
+            Some(field)
+        }
+        else
+        {
+            None
+        }
+    }
+
+    pub(super) fn edit_template_field(&mut self, field_index: usize, value: &str) -> Result<(), String>
+    {
+        let template = self.template.as_ref().ok_or("No template applied")?;
+        let field = &template.fields[field_index];
+        let editor = field.editor.as_ref().ok_or(format!("Field {} can't be edited", field.name))?;
+        let bytes = editor.encode(value, &self.header)?;
+        let (name, offset) = (field.name.clone(), field.offset);
+        if offset + bytes.len() > self.data.len()
+        {
+            return Err(format!("Not enough bytes to write {} at {:#X}", name, offset));
+        }
+        self.write_bytes(offset, &bytes);
+        self.log(NotificationLevel::Debug, &format!("Wrote {} = {} at {:#X}", name, value, offset));
+        self.reapply_template()
+    }
+
+    pub(super) fn get_template_edit_value(&self, field_index: usize) -> String
+    {
+        let field = self.template.as_ref().map(|t| &t.fields[field_index]);
+        match field.and_then(|f| f.value.as_ref().map(|v| (f, v)))
+        {
+            Some((field, value)) => match field.editor
+            {
+                Some(FieldEditor::Primitive(_)) | Some(FieldEditor::Enum(..)) =>
+                    value.split_whitespace().next().unwrap_or_default().to_string(),
+                _ => value.clone(),
+            },
+            None => String::new(),
+        }
+    }
+
+    fn template_field_to_line(color_settings: &ColorSettings, field: &TemplateField, selected: bool) -> Line<'a>
+    {
+        let mut line = Line::default();
+        line.spans.push(Span::raw("  ".repeat(field.depth)));
+        line.spans.push(Span::styled(field.name.clone(), if selected
+        {
+            color_settings.template_selected
+        }
+        else
+        {
+            color_settings.template_name
+        }));
+        line.spans.push(Span::styled(format!(": {}", field.type_name), color_settings.template_type));
+        if let Some(value) = &field.value
+        {
+            line.spans.push(Span::styled(" = ", color_settings.template_type));
+            line.spans.push(Span::styled(value.clone(), color_settings.template_value));
+        }
+        line.spans.push(Span::styled(format!(" @{:X}", field.offset), color_settings.assembly_virtual_address));
+        line
+    }
+
+    pub(super) fn template_view(&self, lines: usize) -> Text<'a>
+    {
+        let mut text = Text::default();
+        if let Some(template) = &self.template
+        {
+            let selected = template.field_at(self.get_cursor_position().global_byte_index);
+            let center = selected.unwrap_or(0);
+            let scroll = center.saturating_sub(lines / 2).min(template.fields.len().saturating_sub(lines));
+            for (i, field) in template.fields.iter().enumerate().skip(scroll).take(lines)
+            {
+                text.lines.push(Self::template_field_to_line(&self.color_settings, field, selected == Some(i)));
+            }
+        }
+        else
+        {
+            text.lines.push(Line::styled("No template applied, press T to load one.", self.color_settings.placeholder));
+        }
+        text
+    }
+}
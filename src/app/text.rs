@@ -68,8 +68,7 @@ impl <'a> App<'a>
 
         if self.text_last_byte_index < self.data.len()
         {
-            let old_byte = self.data[self.text_last_byte_index];
-            let style = Self::get_style_for_byte(&self.color_settings, old_byte);
+            let style = self.get_style_for_byte_at(self.text_last_byte_index);
             self.text_view.lines[self.text_cursor.0].spans[self.text_cursor.1].style = style;
         }
 
@@ -3,4 +3,6 @@ pub struct Args
 {
     #[clap(index = 1, help = "The file to open in the hex editor")]
     pub file: std::path::PathBuf,
+    #[clap(long, help = "Run a Rhai script on the file without the user interface and save the changes")]
+    pub script: Option<std::path::PathBuf>,
 }
\ No newline at end of file
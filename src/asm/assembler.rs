@@ -1,12 +1,13 @@
-use std::{error::Error, io::{Read, Write}, process::{Command, Stdio}};
+use std::{error::Error, io::Write, path::Path, process::{Command, Stdio}};
 
-fn discover_assembler() -> Option<String> {
-    // List of common assembler executables
-    let assemblers = ["nasm", "yasm", "as", "llvm-as"];
+use object::{Object, ObjectSection, ObjectSymbol, RelocationKind, RelocationTarget, SectionKind};
 
-    // Search for each assembler in PATH
-    for assembler in &assemblers {
-        if let Ok(path) = which::which(assembler) {
+use super::syntax::Syntax;
+
+pub(super) fn discover(executables: &[&str]) -> Option<String> {
+    // Search for each executable in PATH
+    for executable in executables {
+        if let Ok(path) = which::which(executable) {
             return Some(path.to_string_lossy().into_owned());
         }
     }
@@ -14,40 +15,315 @@ fn discover_assembler() -> Option<String> {
     None
 }
 
-pub fn assemble(asm: &str, bitness: u32, starting_virtual_address: u64) -> Result<Vec<u8>, Box<dyn Error>> {
-    let mut input_file = tempfile::NamedTempFile::new()?;
-    let output_file = tempfile::NamedTempFile::new()?;
+fn discover_assembler(syntax: Syntax, bitness: u32) -> Option<String> {
+    // List of common assembler executables for each syntax
+    let assemblers: &[&str] = match (syntax, bitness) {
+        (Syntax::Nasm, _) => &["nasm", "yasm"],
+        (Syntax::GasAtt | Syntax::GasIntel, _) => &["as", "llvm-mc"],
+        (Syntax::Masm, 64) => &["ml64", "llvm-ml"],
+        (Syntax::Masm, _) => &["ml", "llvm-ml"],
+    };
+    discover(assemblers)
+}
+
+pub(super) fn discover_linker() -> Option<String> {
+    discover(&["ld", "ld.lld"])
+}
+
+fn is_executable(path: &str, name: &str) -> bool {
+    Path::new(path).file_stem().is_some_and(|stem| stem.to_string_lossy() == name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: DiagnosticLevel,
+    // line and column in the patch, starting from 1
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "line {}, column {}: {}", line, column, self.message),
+            (Some(line), None) => write!(f, "line {}: {}", line, self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblerError {
+    pub tool: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
 
-    input_file.write_all(format!("bits {}\n", bitness).as_bytes())?;
-    if starting_virtual_address != 0
-    {
-        input_file.write_all(format!("org {:#x}\n", starting_virtual_address).as_bytes())?;
+impl std::fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.diagnostics.iter().find(|diagnostic| diagnostic.level == DiagnosticLevel::Error) {
+            Some(error) => write!(f, "{} failed: {}", self.tool, error),
+            None => write!(f, "{} failed", self.tool),
+        }
     }
+}
+
+impl Error for AssemblerError {}
 
-    input_file.write_all(asm.as_bytes())?;
+// the level follows the position, "error:" in nasm, yasm, gas and llvm, "error A2008:" in ml, the rest is the message
+fn parse_level(message: &str) -> Option<(DiagnosticLevel, &str)> {
+    [("error", DiagnosticLevel::Error), ("fatal error", DiagnosticLevel::Error), ("fatal", DiagnosticLevel::Error), ("warning", DiagnosticLevel::Warning)]
+        .into_iter()
+        .find_map(|(token, level)| {
+            let rest = message.get(token.len()..).filter(|_| message[..token.len()].eq_ignore_ascii_case(token))?;
+            let rest = match rest.strip_prefix(':') {
+                Some(rest) => rest,
+                None => {
+                    let code = rest.strip_prefix(" A")?;
+                    code.trim_start_matches(|c: char| c.is_ascii_digit()).strip_prefix(':').filter(|_| code.starts_with(|c: char| c.is_ascii_digit()))?
+                },
+            };
+            Some((level, rest))
+        })
+}
 
-    let assembler = discover_assembler().ok_or("No assembler found")?;
+// parses "file:line: error: message" (nasm, yasm, as), "file:line:column: error: message" (llvm-mc, llvm-ml)
+// and "file(line) : error A2008: message" (ml, ml64), the lines of the patch start after the preamble;
+// lines without a position, like the ones of the linker, have the level after the name of the tool
+fn parse_diagnostic(line: &str, input_path: &str, preamble_lines: usize, patch_lines: usize) -> Option<Diagnostic> {
+    let (position, rest) = match line.split_once(input_path) {
+        Some((_, rest)) => {
+            let rest = rest.trim_start_matches([':', '(']);
+            let end = rest.find(|c: char| !(c.is_ascii_digit() || c == ':')).unwrap_or(rest.len());
+            let numbers: Vec<usize> = rest[..end].split(':').filter_map(|number| number.parse().ok()).collect();
+            (numbers, &rest[end..])
+        },
+        None => (Vec::new(), line),
+    };
+    let rest = rest.trim_start_matches([')', ':', ' ']);
+    let level = if position.is_empty() {
+        std::iter::once(rest).chain(rest.match_indices(": ").map(|(index, _)| &rest[index + 2..])).find_map(parse_level)
+    }
+    else {
+        parse_level(rest)
+    };
+    let (level, message) = match level {
+        Some(level) => level,
+        None if line.contains("undefined reference") => (DiagnosticLevel::Error, rest.rsplit(": ").next().unwrap_or(rest)),
+        None => return None,
+    };
+    let line_number = position.first()
+        .and_then(|line| line.checked_sub(preamble_lines))
+        .filter(|line| (1..=patch_lines).contains(line));
+    let column = position.get(1).copied().filter(|_| line_number.is_some());
+    Some(Diagnostic { level, line: line_number, column, message: message.trim().to_string() })
+}
 
-    let out = Command::new(assembler)
-        .arg("-o")
-        .arg(output_file.path())
-        .arg("-f")
-        .arg("bin")
-        .arg("-s")
-        .arg(input_file.path())
+pub(super) fn run(command: &mut Command, tool: &str, input_path: &Path, preamble_lines: usize, patch_lines: usize) -> Result<Vec<Diagnostic>, Box<dyn Error>> {
+    let out = command
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()?
         .wait_with_output()?;
-    
-    if !out.status.success() || out.stderr.len() > 0 || out.stdout.len() > 0 {
-        let error_str = String::from_utf8_lossy(&out.stdout);
-        let error = error_str.split(':').last().unwrap_or("Unknown error").trim();
-        return Err(format!("Assembler failed: {}", error).into());
+
+    let output = format!("{}{}", String::from_utf8_lossy(&out.stdout), String::from_utf8_lossy(&out.stderr));
+    let input_path = input_path.to_string_lossy();
+    let mut diagnostics: Vec<Diagnostic> = output.lines()
+        .filter_map(|line| parse_diagnostic(line, &input_path, preamble_lines, patch_lines))
+        .collect();
+    // some assemblers report the same error more than once
+    diagnostics.dedup();
+    let has_errors = diagnostics.iter().any(|diagnostic| diagnostic.level == DiagnosticLevel::Error);
+    if !out.status.success() || has_errors {
+        if !has_errors {
+            let message = output.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("Unknown error");
+            diagnostics.push(Diagnostic { level: DiagnosticLevel::Error, line: None, column: None, message: message.trim().to_string() });
+        }
+        return Err(Box::new(AssemblerError { tool: tool.to_string(), diagnostics }));
+    }
+    Ok(diagnostics)
+}
+
+// symbol names can be demangled C++ names, only letters, digits and underscores are kept
+pub fn sanitize_symbol(name: &str) -> String {
+    let name = name.split('@').next().unwrap_or_default();
+    let name = match name.find('(') {
+        Some(index) if index > 0 => &name[..index],
+        _ => name,
+    };
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            sanitized.push(c);
+        }
+        else if !sanitized.ends_with('_') {
+            sanitized.push('_');
+        }
+    }
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+// returns the preamble and the end of the source file around the patch
+fn source(bitness: u32, starting_virtual_address: u64, syntax: Syntax, symbols: &[(String, u64)]) -> Result<(String, String), Box<dyn Error>> {
+    Ok(match syntax {
+        Syntax::Nasm => {
+            let mut preamble = format!("bits {}\n", bitness);
+            if starting_virtual_address != 0
+            {
+                preamble.push_str(&format!("org {:#x}\n", starting_virtual_address));
+            }
+            for (name, address) in symbols {
+                preamble.push_str(&format!("{} equ {:#x}\n", name, address));
+            }
+            (preamble, "\n".to_string())
+        },
+        // the address and the symbols are set by the linker
+        Syntax::GasAtt | Syntax::GasIntel => {
+            let directive = if syntax == Syntax::GasAtt { ".att_syntax prefix" } else { ".intel_syntax noprefix" };
+            (format!("{}\n.code{}\n", directive, bitness), "\n".to_string())
+        },
+        Syntax::Masm => {
+            let model = match bitness {
+                64 => "",
+                32 => ".686P\n.MODEL FLAT\n",
+                _ => return Err("MASM syntax is supported only in 32 and 64 bit mode".into()),
+            };
+            let externs: String = symbols.iter().map(|(name, _)| format!("EXTERN {}:PROC\n", name)).collect();
+            (format!("{}{}_TEXT SEGMENT\n", model, externs), "\n_TEXT ENDS\nEND\n".to_string())
+        },
+    })
+}
+
+// ml, ml64 and llvm-ml write COFF objects that ld usually cannot link, the code section is extracted
+// and its relocations are applied at the starting address here
+fn link_coff(object_path: &Path, starting_virtual_address: u64, symbols: &[(String, u64)]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let data = std::fs::read(object_path)?;
+    let file = object::File::parse(&*data)?;
+    let text = file.sections().find(|section| section.kind() == SectionKind::Text).ok_or("The assembled patch is empty")?;
+    let mut bytes = text.data()?.to_vec();
+    for (offset, relocation) in text.relocations() {
+        let target = match relocation.target() {
+            RelocationTarget::Symbol(index) => {
+                let symbol = file.symbol_by_index(index)?;
+                let name = symbol.name()?;
+                if symbol.is_undefined() {
+                    // 32 bit C names are decorated with an underscore
+                    symbols.iter()
+                        .find(|(symbol, _)| symbol == name || name.strip_prefix('_') == Some(symbol.as_str()))
+                        .map(|(_, address)| *address)
+                        .ok_or(format!("Undefined symbol {}", name))?
+                }
+                else if symbol.section_index() == Some(text.index()) {
+                    starting_virtual_address.wrapping_add(symbol.address())
+                }
+                else {
+                    return Err(format!("{} is not in the code section, MASM patches can only contain code", name).into());
+                }
+            },
+            RelocationTarget::Section(index) if index == text.index() => starting_virtual_address,
+            _ => return Err("Unsupported relocation in the assembled patch".into()),
+        };
+        let size = relocation.size() as usize / 8;
+        let field = bytes.get_mut(offset as usize..offset as usize + size)
+            .filter(|_| (1..=8).contains(&size))
+            .ok_or("Invalid relocation in the assembled patch")?;
+        let mut implicit = [0u8; 8];
+        implicit[..size].copy_from_slice(field);
+        // the addend stored in the field is signed
+        let shift = 64 - 8 * size as u32;
+        let implicit = if relocation.has_implicit_addend() { (i64::from_le_bytes(implicit) << shift) >> shift } else { 0 };
+        let value = target.wrapping_add(implicit.wrapping_add(relocation.addend()) as u64);
+        let value = match relocation.kind() {
+            RelocationKind::Absolute => value,
+            RelocationKind::Relative => value.wrapping_sub(starting_virtual_address.wrapping_add(offset)),
+            kind => return Err(format!("Unsupported relocation {:?} in the assembled patch", kind).into()),
+        };
+        field.copy_from_slice(&value.to_le_bytes()[..size]);
+    }
+    Ok(bytes)
+}
+
+// symbols are pairs of sanitized names and virtual addresses that the assembly can refer to
+pub fn assemble(asm: &str, bitness: u32, starting_virtual_address: u64, syntax: Syntax, symbols: &[(String, u64)]) -> Result<Vec<u8>, Box<dyn Error>> {
+    assemble_with_diagnostics(asm, bitness, starting_virtual_address, syntax, symbols).map(|(bytes, _)| bytes)
+}
+
+// the warnings are returned with the bytes, the errors are returned as an AssemblerError
+pub fn assemble_with_diagnostics(asm: &str, bitness: u32, starting_virtual_address: u64, syntax: Syntax, symbols: &[(String, u64)]) -> Result<(Vec<u8>, Vec<Diagnostic>), Box<dyn Error>> {
+    let extension = if matches!(syntax, Syntax::GasAtt | Syntax::GasIntel) { ".s" } else { ".asm" };
+    let mut input_file = tempfile::Builder::new().suffix(extension).tempfile()?;
+    let output_file = tempfile::NamedTempFile::new()?;
+
+    let (preamble, end) = source(bitness, starting_virtual_address, syntax, symbols)?;
+    input_file.write_all(format!("{}{}{}", preamble, asm, end).as_bytes())?;
+    input_file.flush()?;
+    let preamble_lines = preamble.lines().count();
+    let patch_lines = asm.lines().count().max(1);
+    let input_path = input_file.path();
+
+    let assembler = discover_assembler(syntax, bitness).ok_or(format!("No assembler found for {} syntax", syntax))?;
+
+    let mut diagnostics = Vec::new();
+    if syntax == Syntax::Nasm {
+        let mut command = Command::new(&assembler);
+        command.arg("-o").arg(output_file.path()).arg("-f").arg("bin");
+        if is_executable(&assembler, "nasm") {
+            // nasm writes the errors to stdout
+            command.arg("-s");
+        }
+        diagnostics.extend(run(command.arg(input_path), "Assembler", input_path, preamble_lines, patch_lines)?);
     }
     else {
-        let mut output = Vec::new();
-        output_file.as_file().read_to_end(&mut output)?;
-        Ok(output)
+        // the other assemblers produce an object file that is linked at the starting address
+        let object_file = tempfile::NamedTempFile::new()?;
+        let mut command = Command::new(&assembler);
+        if is_executable(&assembler, "as") {
+            command.arg(if bitness == 64 { "--64" } else { "--32" });
+            command.arg("-o").arg(object_file.path());
+        }
+        else if is_executable(&assembler, "llvm-mc") {
+            command.arg("--filetype=obj").arg(if bitness == 64 { "--triple=x86_64" } else { "--triple=i386" });
+            command.arg("-o").arg(object_file.path());
+        }
+        else {
+            if is_executable(&assembler, "llvm-ml") {
+                command.arg(if bitness == 64 { "-m64" } else { "-m32" });
+            }
+            else {
+                command.arg("/nologo");
+            }
+            command.arg("/c").arg("/Fo").arg(object_file.path());
+        }
+        diagnostics.extend(run(command.arg(input_path), "Assembler", input_path, preamble_lines, patch_lines)?);
+        if syntax == Syntax::Masm {
+            return Ok((link_coff(object_file.path(), starting_virtual_address, symbols)?, diagnostics));
+        }
+
+        let linker = discover_linker().ok_or("No linker found")?;
+        let mut command = Command::new(linker);
+        if bitness != 64 {
+            command.arg("-m").arg("elf_i386");
+        }
+        for (name, address) in symbols {
+            command.arg(format!("--defsym={}={:#x}", name, address));
+        }
+        command.arg(format!("-Ttext={:#x}", starting_virtual_address))
+            .arg("--oformat=binary")
+            .arg("-e").arg("0")
+            .arg("-o").arg(output_file.path())
+            .arg(object_file.path());
+        diagnostics.extend(run(&mut command, "Linker", input_path, preamble_lines, patch_lines)?);
     }
-}
\ No newline at end of file
+
+    // the linker can replace the output file instead of writing to it
+    Ok((std::fs::read(output_file.path())?, diagnostics))
+}
@@ -0,0 +1,122 @@
+use std::{error::Error, io::Write, process::Command};
+
+use object::{Object, ObjectSection, ObjectSymbol};
+
+use super::assembler::{discover, discover_linker, run, Diagnostic};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    Assembly,
+    C,
+}
+
+impl Language {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Assembly => "Assembly",
+            Language::C => "C",
+        }
+    }
+
+    pub fn toggle(&self) -> Language {
+        match self {
+            Language::Assembly => Language::C,
+            Language::C => Language::Assembly,
+        }
+    }
+
+    pub fn from_path(path: &str) -> Language {
+        if path.to_lowercase().ends_with(".c") {
+            Language::C
+        }
+        else {
+            Language::Assembly
+        }
+    }
+}
+
+// everything is placed in .text so that the patch is a single block of position independent code,
+// the data is written after the code and the global offset table is needed by 32 bit code
+const LINKER_SCRIPT: &str = "SECTIONS
+{
+    .text : { *(.text .text.*) *(.rodata .rodata.*) *(.data .data.*) *(.bss .bss.* COMMON) *(.got) *(.got.plt) *(.igot.plt) }
+    /DISCARD/ : { *(.comment) *(.note .note.*) *(.eh_frame) }
+}
+";
+
+fn discover_compiler() -> Option<String> {
+    discover(&["cc", "clang", "gcc"])
+}
+
+fn is_clang(compiler: &str) -> bool {
+    Command::new(compiler)
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("clang"))
+}
+
+fn undefined_symbols(object_path: &std::path::Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let data = std::fs::read(object_path)?;
+    let file = object::File::parse(&*data)?;
+    Ok(file.symbols()
+        .filter(|symbol| symbol.is_undefined())
+        .filter_map(|symbol| symbol.name().ok().map(|name| name.to_string()))
+        .filter(|name| !name.is_empty())
+        .collect())
+}
+
+// the first function of the source is the entry point of the patch, symbols are used only if the source
+// refers to them without defining them, ms_abi selects the Windows calling convention for 64 bit code
+pub fn compile_with_diagnostics(source: &str, bitness: u32, starting_virtual_address: u64, ms_abi: bool, symbols: &[(String, u64)]) -> Result<(Vec<u8>, Vec<Diagnostic>), Box<dyn Error>> {
+    if bitness != 32 && bitness != 64 {
+        return Err("C patches are supported only in 32 and 64 bit mode".into());
+    }
+    let mut input_file = tempfile::Builder::new().suffix(".c").tempfile()?;
+    input_file.write_all(source.as_bytes())?;
+    input_file.flush()?;
+    let mut script_file = tempfile::Builder::new().suffix(".ld").tempfile()?;
+    script_file.write_all(LINKER_SCRIPT.as_bytes())?;
+    script_file.flush()?;
+    let object_file = tempfile::NamedTempFile::new()?;
+    let output_file = tempfile::NamedTempFile::new()?;
+    let patch_lines = source.lines().count().max(1);
+    let input_path = input_file.path();
+
+    let compiler = discover_compiler().ok_or("No C compiler found")?;
+    let mut command = Command::new(&compiler);
+    command.arg(if bitness == 64 { "-m64" } else { "-m32" })
+        .args(["-c", "-Os", "-g0", "-fPIC", "-ffreestanding", "-fno-builtin", "-fno-stack-protector"])
+        .args(["-fno-asynchronous-unwind-tables", "-fcf-protection=none", "-fno-diagnostics-show-caret"]);
+    if ms_abi && bitness == 64 {
+        command.arg("-mabi=ms");
+    }
+    if !is_clang(&compiler) {
+        // gcc can move functions and cold blocks away from the entry point
+        command.args(["-fno-toplevel-reorder", "-fno-reorder-blocks-and-partition"]);
+    }
+    command.arg("-o").arg(object_file.path()).arg(input_path);
+    let mut diagnostics = run(&mut command, "Compiler", input_path, 0, patch_lines)?;
+
+    let undefined = undefined_symbols(object_file.path())?;
+    let linker = discover_linker().ok_or("No linker found")?;
+    let mut command = Command::new(linker);
+    if bitness != 64 {
+        command.arg("-m").arg("elf_i386");
+    }
+    for (name, address) in symbols.iter().filter(|(name, _)| undefined.contains(name)) {
+        command.arg(format!("--defsym={}={:#x}", name, address));
+    }
+    command.arg("-T").arg(script_file.path())
+        .arg(format!("-Ttext={:#x}", starting_virtual_address))
+        .arg("-e").arg("0")
+        .arg("-o").arg(output_file.path())
+        .arg(object_file.path());
+    diagnostics.extend(run(&mut command, "Linker", input_path, 0, patch_lines)?);
+
+    // ld creates the global offset table only when it writes an ELF file, the code is extracted afterwards
+    let data = std::fs::read(output_file.path())?;
+    let file = object::File::parse(&*data)?;
+    let text = file.section_by_name(".text").ok_or("The compiled patch is empty")?;
+    Ok((text.data()?.to_vec(), diagnostics))
+}
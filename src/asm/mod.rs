@@ -1 +1,4 @@
-pub mod assembler;
\ No newline at end of file
+pub mod assembler;
+pub mod compiler;
+pub mod nop;
+pub mod syntax;
\ No newline at end of file
@@ -0,0 +1,28 @@
+// multi-byte NOPs recommended by the Intel optimization manual, valid both in 32 and 64 bit mode
+const NOPS: [&[u8]; 9] = [
+    &[0x90],
+    &[0x66, 0x90],
+    &[0x0F, 0x1F, 0x00],
+    &[0x0F, 0x1F, 0x40, 0x00],
+    &[0x0F, 0x1F, 0x44, 0x00, 0x00],
+    &[0x66, 0x0F, 0x1F, 0x44, 0x00, 0x00],
+    &[0x0F, 0x1F, 0x80, 0x00, 0x00, 0x00, 0x00],
+    &[0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+    &[0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+];
+
+pub fn nops(bitness: u32, size: usize) -> Vec<u8>
+{
+    // the multi-byte NOPs have a different length in 16 bit mode
+    if bitness == 16
+    {
+        return vec![0x90; size];
+    }
+    let mut ret = Vec::with_capacity(size);
+    while ret.len() < size
+    {
+        let remaining = (size - ret.len()).min(NOPS.len());
+        ret.extend_from_slice(NOPS[remaining - 1]);
+    }
+    ret
+}
@@ -0,0 +1,122 @@
+use iced_x86::{Formatter, GasFormatter, IntelFormatter, MasmFormatter, NasmFormatter, SymbolResolver};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Syntax
+{
+    #[default]
+    Nasm,
+    GasAtt,
+    GasIntel,
+    Masm,
+}
+
+impl Syntax
+{
+    pub fn all() -> [Syntax; 4]
+    {
+        [Syntax::Nasm, Syntax::GasAtt, Syntax::GasIntel, Syntax::Masm]
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Syntax::Nasm => "nasm",
+            Syntax::GasAtt => "gas_att",
+            Syntax::GasIntel => "gas_intel",
+            Syntax::Masm => "masm",
+        }
+    }
+
+    pub fn description(&self) -> &'static str
+    {
+        match self
+        {
+            Syntax::Nasm => "NASM, assembled with nasm or yasm",
+            Syntax::GasAtt => "GNU AT&T, assembled with as or llvm-mc and linked with ld",
+            Syntax::GasIntel => "GNU Intel, assembled with as or llvm-mc and linked with ld",
+            Syntax::Masm => "MASM, assembled with ml, ml64 or llvm-ml",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Syntax>
+    {
+        Self::all().into_iter().find(|syntax| syntax.name() == name.trim().to_lowercase())
+    }
+
+    // the disassembly is printed with the same syntax so that it can be copied into a patch
+    pub fn formatter(&self, symbol_resolver: Option<Box<dyn SymbolResolver>>) -> Box<dyn Formatter>
+    {
+        match self
+        {
+            Syntax::Nasm => Box::new(NasmFormatter::with_options(symbol_resolver, None)),
+            // the GNU assemblers read [address] as an absolute address, rip relative operands are printed as [rip+offset]
+            Syntax::GasAtt =>
+            {
+                let mut formatter = GasFormatter::with_options(symbol_resolver, None);
+                formatter.options_mut().set_rip_relative_addresses(true);
+                Box::new(formatter)
+            },
+            Syntax::GasIntel =>
+            {
+                let mut formatter = IntelFormatter::with_options(symbol_resolver, None);
+                formatter.options_mut().set_rip_relative_addresses(true);
+                formatter.options_mut().set_hex_prefix("0x");
+                formatter.options_mut().set_hex_suffix("");
+                Box::new(formatter)
+            },
+            Syntax::Masm => Box::new(MasmFormatter::with_options(symbol_resolver, None)),
+        }
+    }
+
+    // data is printed with the directives of the assembler so that it can be copied into a patch too
+    pub fn data_directive(&self, size: usize) -> &'static str
+    {
+        match (self, size)
+        {
+            (Syntax::GasAtt | Syntax::GasIntel, 1) => ".byte",
+            (Syntax::GasAtt | Syntax::GasIntel, 2) => ".word",
+            (Syntax::GasAtt | Syntax::GasIntel, 4) => ".long",
+            (Syntax::GasAtt | Syntax::GasIntel, _) => ".quad",
+            (_, 1) => "db",
+            (_, 2) => "dw",
+            (_, 4) => "dd",
+            (_, _) => "dq",
+        }
+    }
+
+    pub fn string_directive(&self, null_terminated: bool) -> &'static str
+    {
+        match (self, null_terminated)
+        {
+            (Syntax::GasAtt | Syntax::GasIntel, true) => ".asciz",
+            (Syntax::GasAtt | Syntax::GasIntel, false) => ".ascii",
+            (_, _) => "db",
+        }
+    }
+
+    // the same notation used by the formatter for the instructions
+    pub fn format_number(&self, value: u64) -> String
+    {
+        match self
+        {
+            Syntax::GasAtt | Syntax::GasIntel => format!("{:#x}", value),
+            Syntax::Nasm | Syntax::Masm if value < 10 => value.to_string(),
+            Syntax::Nasm | Syntax::Masm =>
+            {
+                let hex = format!("{:X}", value);
+                if hex.starts_with(|c: char| c.is_ascii_alphabetic()) { format!("0{}h", hex) } else { format!("{}h", hex) }
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Syntax
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        write!(f, "{}", self.name())
+    }
+}
@@ -21,4 +21,17 @@ impl Fuzzer
         ret.sort_by_key(|entry| -entry.score(key));
         ret.into_iter().map(|entry| entry.key).collect()
     }
+
+    pub fn fuzzy_filter(&self, key: &str) -> Vec<usize>
+    {
+        let key_len = key.chars().count() as isize;
+        let mut ret: Vec<(usize, bool)> = self.entries.iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.score(key) == key_len)
+            .map(|(i, entry)| (i, entry.key.contains(key)))
+            .collect();
+        // exact substring matches come first
+        ret.sort_by_key(|(_, contains)| !contains);
+        ret.into_iter().map(|(i, _)| i).collect()
+    }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use std::{collections::HashMap, rc::Rc};
 
-use object::{read::elf::{ElfFile32, ElfFile64}, BigEndian, LittleEndian, Object, ObjectSection, ObjectSymbol, SectionKind};
+use object::{read::elf::{ElfFile, ElfFile32, ElfFile64, FileHeader, Sym}, BigEndian, LittleEndian, Object, ObjectSection, ObjectSymbol, SectionKind};
+
+use super::header::{Fixup, Relocation, SymbolLocation};
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum Bitness
@@ -48,7 +50,9 @@ pub struct ElfHeader
     pub entry_point: u64,
     pub section_table: Vec<Section>,
     pub symbol_table: Rc<HashMap<u64, String>>,
-    pub inverse_symbol_table: HashMap<String, u64>
+    pub inverse_symbol_table: HashMap<String, u64>,
+    pub symbol_locations: HashMap<u64, SymbolLocation>,
+    pub relocations: Vec<Relocation>,
 }
 
 enum ElfVariant<'data>
@@ -163,31 +167,35 @@ impl ElfHeader
                 }).collect(),
         };
 
-        let sections = sections.into_iter().filter(|s| s.size != 0).collect();
+        let sections: Vec<Section> = sections.into_iter().filter(|s| s.size != 0).collect();
+        let relocations = Self::read_relocations(bytes, &sections);
 
-        let symbols: Vec<(u64, String)> = match header
+        let symbols = match header
         {
-            ElfVariant::Elf64Little(h) => h.symbols().map(
-                |s| (s.address(), s.name().map(|n|n.to_string()).unwrap_or(format!("s_{:#x}", s.address())))).collect(),
-            ElfVariant::Elf64Big(h) => h.symbols().map(
-                |s| (s.address(), s.name().map(|n|n.to_string()).unwrap_or(format!("s_{:#x}", s.address())))).collect(),
-            ElfVariant::Elf32Little(h) => h.symbols().map(
-                |s| (s.address(), s.name().map(|n|n.to_string()).unwrap_or(format!("s_{:#x}", s.address())))).collect(),
-            ElfVariant::Elf32Big(h) => h.symbols().map(
-                |s| (s.address(), s.name().map(|n|n.to_string()).unwrap_or(format!("s_{:#x}", s.address())))).collect(),
+            ElfVariant::Elf64Little(h) => Self::read_symbols(&h),
+            ElfVariant::Elf64Big(h) => Self::read_symbols(&h),
+            ElfVariant::Elf32Little(h) => Self::read_symbols(&h),
+            ElfVariant::Elf32Big(h) => Self::read_symbols(&h),
         };
 
-        let symbols: HashMap<u64, String> = symbols.into_iter().map(|(k,v)| {
+        let mut symbol_locations = HashMap::new();
+        let symbols: HashMap<u64, String> = symbols.into_iter().map(|(k,v,location)| {
             let mangled_name = v;
             let demangled_name = cpp_demangle::Symbol::new(mangled_name.as_str());
-            if let Ok(name) = demangled_name
+            let name = if let Ok(name) = demangled_name
             {
-                (k, name.to_string())
+                name.to_string()
             }
             else
             {
-                (k, mangled_name)
-            }
+                mangled_name
+            };
+            match location
+            {
+                Some(location) => symbol_locations.insert(k, SymbolLocation { name: name.clone(), ..location }),
+                None => symbol_locations.remove(&k),
+            };
+            (k, name)
         }).collect();
 
         let inverse_symbol_table = symbols.iter().map(|(k,v)| (v.clone(), *k)).collect();
@@ -198,10 +206,117 @@ impl ElfHeader
             entry_point,
             section_table: sections,
             symbol_table: Rc::new(symbols),
-            inverse_symbol_table
+            inverse_symbol_table,
+            symbol_locations,
+            relocations,
         })
     }
 
+    // only the relocation tables used by the dynamic loader are read
+    fn read_relocations(data: &[u8], sections: &[Section]) -> Vec<Relocation>
+    {
+        let (is_64, endianness) = match Self::parse_ident(data)
+        {
+            Ok(ident) => ident,
+            Err(_) => return Vec::new(),
+        };
+        let read = |offset: usize, size: usize| Self::read_value(data, offset, size, endianness);
+        let word = if is_64 { 8 } else { 4 };
+        let shoff = read(0x18 + 2 * word, word) as usize;
+        let header_end = 0x18 + 3 * word + 4;
+        let (shentsize, shnum) = (read(header_end + 6, 2) as usize, read(header_end + 8, 2) as usize);
+        // offsets of sh_flags, sh_offset, sh_size
+        let sh = if is_64 { [8, 24, 32] } else { [8, 16, 20] };
+        let mut relocations = Vec::new();
+        for i in 0..shnum
+        {
+            let entry = match i.checked_mul(shentsize).and_then(|position| shoff.checked_add(position))
+            {
+                Some(entry) if entry.checked_add(shentsize).is_some_and(|end| end <= data.len()) => entry,
+                _ => break,
+            };
+            // SHT_RELA or SHT_REL with SHF_ALLOC
+            let section_type = read(entry + 4, 4);
+            if !(section_type == 4 || section_type == 9) || read(entry + sh[0], word) & 2 == 0
+            {
+                continue;
+            }
+            let entry_size = match (section_type, is_64)
+            {
+                (4, true) => 24,
+                (4, false) => 12,
+                (_, true) => 16,
+                (_, false) => 8,
+            };
+            let (table_offset, table_size) = (read(entry + sh[1], word) as usize, read(entry + sh[2], word) as usize);
+            let table_end = match table_offset.checked_add(table_size)
+            {
+                Some(table_end) => table_end.min(data.len()),
+                None => continue,
+            };
+            for relocation in (table_offset..table_end).step_by(entry_size)
+            {
+                if relocation + entry_size > data.len()
+                {
+                    break;
+                }
+                let (address, info) = (read(relocation, word), read(relocation + word, word));
+                let (relocation_type, info_without_type) = if is_64 { (info & 0xFFFFFFFF, info & !0xFFFFFFFF) } else { (info & 0xFF, info & !0xFF) };
+                let offset = sections.iter()
+                    .find(|section| address >= section.address && section.address.checked_add(section.size).is_some_and(|end| address < end))
+                    .and_then(|section| section.offset.checked_add(address - section.address));
+                if let (Some(offset), true) = (offset, relocation_type != 0)
+                {
+                    // R_X86_64_NONE and R_386_NONE are both 0
+                    let mut bytes = vec![0; word];
+                    Self::write_value(&mut bytes, 0, word, endianness, info_without_type);
+                    relocations.push(Relocation
+                    {
+                        offset,
+                        size: word as u64,
+                        removal: Fixup
+                        {
+                            offset: (relocation + word) as u64,
+                            bytes,
+                            // the loader can apply the relative relocations counted by DT_RELACOUNT without checking their type
+                            description: if relocation_type == 8
+                            {
+                                format!("Removed relative relocation at {:#X}, the loader may still apply it if DT_RELACOUNT counts it", address)
+                            }
+                            else
+                            {
+                                format!("Removed relocation of type {} at {:#X}", relocation_type, address)
+                            },
+                        },
+                    });
+                }
+            }
+        }
+        relocations.sort_by_key(|relocation| relocation.offset);
+        relocations
+    }
+
+    fn read_symbols<Elf: FileHeader>(file: &ElfFile<Elf>) -> Vec<(u64, String, Option<SymbolLocation>)>
+    {
+        let endian = file.endian();
+        let string_table = file.section_by_name(".strtab").and_then(|s| s.file_range()).map(|(offset, _)| offset);
+        let names: Vec<(u64, usize)> = file.symbols()
+            .map(|s| (s.raw_symbol().st_name(endian) as u64, s.name_bytes().map(|n| n.len()).unwrap_or(0)))
+            .collect();
+        // linkers merge names that are suffixes of other names, those can't be overwritten safely
+        let mut names_per_end = HashMap::new();
+        for (start, len) in names.iter()
+        {
+            *names_per_end.entry(start + *len as u64).or_insert(0) += 1;
+        }
+        file.symbols().zip(names.iter()).map(|(s, (start, len))| {
+            let name = s.name().map(|n|n.to_string()).unwrap_or(format!("s_{:#x}", s.address()));
+            let location = string_table.filter(|_| *len > 0 && names_per_end[&(start + *len as u64)] == 1)
+                .map(|offset| SymbolLocation { offset: offset + start, capacity: *len, name: String::new() });
+            (s.address(), name, location)
+        }).collect()
+    }
+
     pub fn bitness(&self) -> u32
     {
         self.bitness.to_num_bits()
@@ -213,6 +328,245 @@ impl ElfHeader
         dbg!(header);
     }
 
+    fn parse_ident(data: &[u8]) -> Result<(bool, Endianness), String>
+    {
+        if data.len() < 0x34 || data[0..4] != [0x7F, b'E', b'L', b'F']
+        {
+            return Err("The ELF signature is not valid".to_string());
+        }
+        let is_64 = match data[4]
+        {
+            1 => false,
+            2 => true,
+            _ => return Err("The ELF class is not valid".to_string()),
+        };
+        let endianness = match data[5]
+        {
+            1 => Endianness::Little,
+            2 => Endianness::Big,
+            _ => return Err("The ELF data encoding is not valid".to_string()),
+        };
+        Ok((is_64, endianness))
+    }
+
+    fn read_value(data: &[u8], offset: usize, size: usize, endianness: Endianness) -> u64
+    {
+        let mut value = 0u64;
+        for i in 0..size
+        {
+            let byte = data.get(offset + i).cloned().unwrap_or(0) as u64;
+            value |= match endianness
+            {
+                Endianness::Little => byte << (i * 8),
+                Endianness::Big => byte << ((size - 1 - i) * 8),
+            };
+        }
+        value
+    }
+
+    fn write_value(data: &mut [u8], offset: usize, size: usize, endianness: Endianness, value: u64)
+    {
+        for i in 0..size
+        {
+            let shift = match endianness
+            {
+                Endianness::Little => i * 8,
+                Endianness::Big => (size - 1 - i) * 8,
+            };
+            data[offset + i] = (value >> shift) as u8;
+        }
+    }
+
+    // appends a new executable PT_LOAD segment, the program header table is moved at its start since
+    // there is usually no space to grow it in place, a section header is added to describe the code
+    pub fn add_segment(data: &[u8], name: &str, size: u64) -> Result<(Vec<u8>, u64), String>
+    {
+        let (is_64, endianness) = Self::parse_ident(data)?;
+        let read = |data: &[u8], offset: usize, size: usize| Self::read_value(data, offset, size, endianness);
+        let write = |data: &mut [u8], offset: usize, size: usize, value: u64| Self::write_value(data, offset, size, endianness, value);
+        let word = if is_64 { 8 } else { 4 };
+        let (phoff, shoff) = (read(data, 0x18 + word, word) as usize, read(data, 0x18 + 2 * word, word) as usize);
+        let header_end = 0x18 + 3 * word + 4;
+        let (phentsize, phnum) = (read(data, header_end + 2, 2) as usize, read(data, header_end + 4, 2) as usize);
+        let (shentsize, shnum, shstrndx) = (read(data, header_end + 6, 2) as usize, read(data, header_end + 8, 2) as usize, read(data, header_end + 10, 2) as usize);
+        if phnum == 0
+        {
+            return Err("The file has no program headers".to_string());
+        }
+        if phoff + phnum * phentsize > data.len() || shoff + shnum * shentsize > data.len()
+        {
+            return Err("The header tables are outside the file".to_string());
+        }
+        // offsets of p_offset, p_vaddr, p_paddr, p_filesz, p_memsz, p_flags, p_align
+        let ph = if is_64 { [8, 16, 24, 32, 40, 4, 48] } else { [4, 8, 12, 16, 20, 24, 28] };
+
+        let page_size = 0x1000u64;
+        let mut max_address = 0u64;
+        let mut load_bias = None;
+        for i in 0..phnum
+        {
+            let entry = phoff + i * phentsize;
+            if read(data, entry, 4) == 1
+            {
+                let (offset, address, memory_size) = (read(data, entry + ph[0], word), read(data, entry + ph[1], word), read(data, entry + ph[4], word));
+                max_address = max_address.max(address + memory_size);
+                load_bias.get_or_insert(address.wrapping_sub(offset));
+            }
+        }
+        let segment_offset = (data.len() as u64).next_multiple_of(page_size);
+        // keeping the same offset to address bias of the first segment lets older loaders find the program headers
+        let mut segment_address = segment_offset.wrapping_add(load_bias.unwrap_or(0));
+        if segment_address < max_address
+        {
+            segment_address += (max_address - segment_address).next_multiple_of(page_size);
+        }
+        let program_headers_size = ((phnum + 1) * phentsize) as u64;
+        let code_offset = (segment_offset + program_headers_size).next_multiple_of(16);
+        let code_address = segment_address + code_offset - segment_offset;
+        let segment_size = code_offset + size - segment_offset;
+
+        let mut program_headers = data[phoff..phoff + phnum * phentsize].to_vec();
+        program_headers.resize(program_headers_size as usize, 0);
+        let new_entry = phnum * phentsize;
+        write(&mut program_headers, new_entry, 4, 1);
+        write(&mut program_headers, new_entry + ph[0], word, segment_offset);
+        write(&mut program_headers, new_entry + ph[1], word, segment_address);
+        write(&mut program_headers, new_entry + ph[2], word, segment_address);
+        write(&mut program_headers, new_entry + ph[3], word, segment_size);
+        write(&mut program_headers, new_entry + ph[4], word, segment_size);
+        // PF_R | PF_X
+        write(&mut program_headers, new_entry + ph[5], 4, 5);
+        write(&mut program_headers, new_entry + ph[6], word, page_size);
+        for i in 0..phnum
+        {
+            // PT_PHDR must describe the new program header table
+            let entry = i * phentsize;
+            if read(&program_headers, entry, 4) == 6
+            {
+                write(&mut program_headers, entry + ph[0], word, segment_offset);
+                write(&mut program_headers, entry + ph[1], word, segment_address);
+                write(&mut program_headers, entry + ph[2], word, segment_address);
+                write(&mut program_headers, entry + ph[3], word, program_headers_size);
+                write(&mut program_headers, entry + ph[4], word, program_headers_size);
+            }
+        }
+
+        let mut new_data = data.to_vec();
+        new_data.resize(segment_offset as usize, 0);
+        new_data.extend(program_headers);
+        new_data.resize((code_offset + size) as usize, 0);
+        write(&mut new_data, 0x18 + word, word, segment_offset);
+        write(&mut new_data, header_end + 4, 2, phnum as u64 + 1);
+
+        if shnum > 0 && shstrndx < shnum
+        {
+            // offsets of sh_flags, sh_addr, sh_offset, sh_size, sh_addralign
+            let sh = if is_64 { [8, 16, 24, 32, 48] } else { [8, 12, 16, 20, 32] };
+            let string_table_entry = shoff + shstrndx * shentsize;
+            let (strings_offset, strings_size) = (read(data, string_table_entry + sh[2], word) as usize, read(data, string_table_entry + sh[3], word) as usize);
+            let mut strings = data.get(strings_offset..strings_offset + strings_size).ok_or("The section name table is outside the file")?.to_vec();
+            let name_offset = strings.len() as u64;
+            strings.extend(name.as_bytes());
+            strings.push(0);
+            let new_strings_offset = new_data.len() as u64;
+            new_data.extend(&strings);
+            new_data.resize(new_data.len().next_multiple_of(word), 0);
+
+            let mut section_headers = data[shoff..shoff + shnum * shentsize].to_vec();
+            section_headers.resize((shnum + 1) * shentsize, 0);
+            write(&mut section_headers, shstrndx * shentsize + sh[2], word, new_strings_offset);
+            write(&mut section_headers, shstrndx * shentsize + sh[3], word, strings.len() as u64);
+            let new_entry = shnum * shentsize;
+            write(&mut section_headers, new_entry, 4, name_offset);
+            // SHT_PROGBITS with SHF_ALLOC | SHF_EXECINSTR
+            write(&mut section_headers, new_entry + 4, 4, 1);
+            write(&mut section_headers, new_entry + sh[0], word, 6);
+            write(&mut section_headers, new_entry + sh[1], word, code_address);
+            write(&mut section_headers, new_entry + sh[2], word, code_offset);
+            write(&mut section_headers, new_entry + sh[3], word, size);
+            write(&mut section_headers, new_entry + sh[4], word, 16);
+            let new_shoff = new_data.len() as u64;
+            new_data.extend(section_headers);
+            write(&mut new_data, 0x18 + 2 * word, word, new_shoff);
+            write(&mut new_data, header_end + 8, 2, shnum as u64 + 1);
+        }
+        Ok((new_data, code_offset))
+    }
+
+    // checks that the tables in the header still describe the file after it has been edited
+    pub fn check_tables(data: &[u8]) -> Vec<String>
+    {
+        let mut warnings = Vec::new();
+        let (is_64, endianness) = match Self::parse_ident(data)
+        {
+            Ok(ident) => ident,
+            Err(e) =>
+            {
+                warnings.push(e);
+                return warnings;
+            }
+        };
+        let read = |offset: usize, size: usize| Self::read_value(data, offset, size, endianness);
+        let word = if is_64 { 8 } else { 4 };
+        let (phoff, shoff) = (read(0x18 + word, word), read(0x18 + 2 * word, word));
+        let header_end = 0x18 + 3 * word + 4;
+        let (phentsize, phnum) = (read(header_end + 2, 2), read(header_end + 4, 2));
+        let (shentsize, shnum, shstrndx) = (read(header_end + 6, 2), read(header_end + 8, 2), read(header_end + 10, 2));
+        let file_size = data.len() as u64;
+
+        // the values come from the file, the sums can overflow
+        match phoff.checked_add(phentsize * phnum)
+        {
+            None => warnings.push(format!("The program header table at {:#X} overflows the address space", phoff)),
+            Some(end) if phnum > 0 && end > file_size => warnings.push(format!("The program header table [{:#X} - {:#X}) is outside the file", phoff, end)),
+            Some(_) =>
+            {
+                for i in 0..phnum
+                {
+                    let entry = (phoff + i * phentsize) as usize;
+                    let (offset, size) = if is_64 { (read(entry + 8, 8), read(entry + 32, 8)) } else { (read(entry + 4, 4), read(entry + 16, 4)) };
+                    match offset.checked_add(size)
+                    {
+                        None => warnings.push(format!("Segment {} at {:#X} overflows the address space", i, offset)),
+                        Some(end) if end > file_size => warnings.push(format!("Segment {} [{:#X} - {:#X}) is outside the file", i, offset, end)),
+                        Some(_) => {},
+                    }
+                }
+            }
+        }
+
+        match shoff.checked_add(shentsize * shnum)
+        {
+            None => warnings.push(format!("The section header table at {:#X} overflows the address space", shoff)),
+            Some(end) if shnum > 0 && end > file_size => warnings.push(format!("The section header table [{:#X} - {:#X}) is outside the file", shoff, end)),
+            Some(_) =>
+            {
+                for i in 0..shnum
+                {
+                    let entry = (shoff + i * shentsize) as usize;
+                    let section_type = read(entry + 4, 4);
+                    let (offset, size) = if is_64 { (read(entry + 24, 8), read(entry + 32, 8)) } else { (read(entry + 16, 4), read(entry + 20, 4)) };
+                    // SHT_NOBITS sections don't occupy space in the file
+                    if section_type == 8
+                    {
+                        continue;
+                    }
+                    match offset.checked_add(size)
+                    {
+                        None => warnings.push(format!("Section {} at {:#X} overflows the address space", i, offset)),
+                        Some(end) if end > file_size => warnings.push(format!("Section {} [{:#X} - {:#X}) is outside the file", i, offset, end)),
+                        Some(_) => {},
+                    }
+                }
+                if shnum > 0 && shstrndx >= shnum
+                {
+                    warnings.push(format!("The section name table index {} is not a valid section", shstrndx));
+                }
+            }
+        }
+        warnings
+    }
+
     pub fn get_symbols(&self) -> Rc<HashMap<u64,String>>
     {
         self.symbol_table.clone()
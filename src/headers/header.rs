@@ -1,6 +1,8 @@
 use std::{collections::HashMap, fmt::Display, rc::Rc};
 
-use super::{elf::ElfHeader, pe::PEHeader};
+use object::SectionKind;
+
+use super::{elf::{ElfHeader, Endianness}, pe::PEHeader};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Section
@@ -19,6 +21,36 @@ impl Display for Section
     }
 }
 
+// where the name of a symbol is stored in the file, used to write renamed symbols back
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SymbolLocation
+{
+    pub offset: u64,
+    pub capacity: usize,
+    pub name: String,
+}
+
+// bytes changed when saving the file to keep the headers consistent
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Fixup
+{
+    pub offset: u64,
+    pub bytes: Vec<u8>,
+    pub description: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Relocation
+{
+    // file offset and size of the bytes written by the loader
+    pub offset: u64,
+    pub size: u64,
+    // bytes that disable the entry in the relocation table
+    pub removal: Fixup,
+}
+
+type SymbolTablesMut<'a> = (&'a mut Rc<HashMap<u64, String>>, &'a mut HashMap<String, u64>, &'a mut HashMap<u64, SymbolLocation>);
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Header
 {
@@ -56,6 +88,16 @@ impl Header
         }
     }
 
+    pub fn endianness(&self) -> Endianness
+    {
+        match self
+        {
+            Header::Elf(header) => header.endianness,
+            Header::PE(_) => Endianness::Little,
+            Header::None => Endianness::Little,
+        }
+    }
+
     pub fn entry_point(&self) -> u64
     {
         match self
@@ -147,6 +189,66 @@ impl Header
         }
     }
 
+    pub fn get_code_sections(&self) -> Vec<Section>
+    {
+        match self
+        {
+            Header::Elf(header) => header.section_table.iter()
+                .filter(|section| section.section_type == SectionKind::Text)
+                .map(|section| Section
+                {
+                    name: section.name.clone(),
+                    virtual_address: section.address,
+                    address: section.offset,
+                    size: section.size,
+                }).collect(),
+            Header::PE(header) => header.section_table.iter()
+                // IMAGE_SCN_CNT_CODE or IMAGE_SCN_MEM_EXECUTE
+                .filter(|section| section.characteristics & 0x20000020 != 0)
+                .map(|section| Section
+                {
+                    name: section.name.clone(),
+                    virtual_address: section.virtual_address as u64,
+                    address: section.pointer_to_raw_data as u64,
+                    size: section.size_of_raw_data as u64,
+                }).collect(),
+            Header::None => Vec::new(),
+        }
+    }
+
+    // the bytes of a PE section after its virtual size are in the file but they are not loaded
+    pub fn get_loaded_code_sections(&self) -> Vec<Section>
+    {
+        match self
+        {
+            Header::PE(header) => self.get_code_sections().into_iter()
+                .map(|mut section|
+                {
+                    let virtual_size = header.section_table.iter()
+                        .find(|pe_section| pe_section.pointer_to_raw_data as u64 == section.address)
+                        .map(|pe_section| pe_section.virtual_size as u64)
+                        .unwrap_or(0);
+                    if virtual_size != 0
+                    {
+                        section.size = section.size.min(virtual_size);
+                    }
+                    section
+                }).collect(),
+            _ => self.get_code_sections(),
+        }
+    }
+
+    // returns the new file contents and the file offset of the new code
+    pub fn add_section(&self, data: &[u8], name: &str, size: u64) -> Result<(Vec<u8>, u64), String>
+    {
+        match self
+        {
+            Header::Elf(_) => ElfHeader::add_segment(data, name, size),
+            Header::PE(_) => PEHeader::add_section(data, name, size),
+            Header::None => Err("Sections can only be added to ELF and PE files".to_string()),
+        }
+    }
+
     pub fn get_symbols(&self) -> Option<Rc<HashMap<u64,String>>>
     {
         match self
@@ -180,6 +282,140 @@ impl Header
         }
     }
 
+    pub fn save_fixups(&self, data: &[u8]) -> (Vec<Fixup>, Vec<String>)
+    {
+        match self
+        {
+            Header::Elf(_) => (Vec::new(), ElfHeader::check_tables(data)),
+            Header::PE(_) => PEHeader::save_fixups(data),
+            Header::None => (Vec::new(), Vec::new()),
+        }
+    }
+
+    fn symbol_tables_mut(&mut self) -> Option<SymbolTablesMut<'_>>
+    {
+        match self
+        {
+            Header::Elf(header) => Some((&mut header.symbol_table, &mut header.inverse_symbol_table, &mut header.symbol_locations)),
+            Header::PE(header) => Some((&mut header.symbol_table, &mut header.inverse_symbol_table, &mut header.symbol_locations)),
+            Header::None => None,
+        }
+    }
+
+    pub fn set_symbol(&mut self, address: u64, name: &str) -> Result<(), String>
+    {
+        if name.is_empty() || name.chars().any(|c| c.is_whitespace())
+        {
+            return Err(format!("Invalid symbol name: \"{}\"", name));
+        }
+        let (symbol_table, inverse_symbol_table, _) = self.symbol_tables_mut().ok_or("The file has no symbol table")?;
+        if let Some(other) = inverse_symbol_table.get(name).filter(|other| **other != address)
+        {
+            return Err(format!("Symbol {} is already defined at {:#X}", name, other));
+        }
+        if let Some(old_name) = Rc::make_mut(symbol_table).insert(address, name.to_string())
+        {
+            if inverse_symbol_table.get(&old_name) == Some(&address)
+            {
+                inverse_symbol_table.remove(&old_name);
+            }
+        }
+        inverse_symbol_table.insert(name.to_string(), address);
+        Ok(())
+    }
+
+    pub fn remove_symbol(&mut self, address: u64) -> Option<String>
+    {
+        let (symbol_table, inverse_symbol_table, _) = self.symbol_tables_mut()?;
+        let old_name = Rc::make_mut(symbol_table).remove(&address)?;
+        if inverse_symbol_table.get(&old_name) == Some(&address)
+        {
+            inverse_symbol_table.remove(&old_name);
+        }
+        Some(old_name)
+    }
+
+    pub fn is_symbol_writable(&self, address: u64) -> bool
+    {
+        match self
+        {
+            Header::Elf(header) => header.symbol_locations.contains_key(&address),
+            Header::PE(header) => header.symbol_locations.contains_key(&address),
+            Header::None => false,
+        }
+    }
+
+    // bytes to write to store the modified symbols in the file and the symbols that don't fit
+    pub fn symbol_patches(&mut self) -> (Vec<(u64, Vec<u8>)>, Vec<String>)
+    {
+        let mut patches = Vec::new();
+        let mut errors = Vec::new();
+        if let Some((symbol_table, _, symbol_locations)) = self.symbol_tables_mut()
+        {
+            for (address, location) in symbol_locations.iter_mut()
+            {
+                let name = symbol_table.get(address).map(|name| name.as_str()).unwrap_or("");
+                if name == location.name
+                {
+                    continue;
+                }
+                if name.len() > location.capacity
+                {
+                    errors.push(format!("Symbol {} is longer than {} bytes and can't be saved", name, location.capacity));
+                    continue;
+                }
+                let mut bytes = name.as_bytes().to_vec();
+                bytes.resize(location.capacity, 0);
+                patches.push((location.offset, bytes));
+                location.name = name.to_string();
+            }
+        }
+        (patches, errors)
+    }
+
+    pub fn get_relocations(&self) -> &[Relocation]
+    {
+        match self
+        {
+            Header::Elf(header) => &header.relocations,
+            Header::PE(header) => &header.relocations,
+            Header::None => &[],
+        }
+    }
+
+    fn relocations_range(&self, from: u64, to: u64) -> std::ops::Range<usize>
+    {
+        let relocations = self.get_relocations();
+        let start = relocations.partition_point(|relocation| relocation.offset + relocation.size <= from);
+        let end = relocations.partition_point(|relocation| relocation.offset < to).max(start);
+        start..end
+    }
+
+    // relocations that overlap the bytes between from and to, excluded
+    pub fn relocations_in(&self, from: u64, to: u64) -> &[Relocation]
+    {
+        &self.get_relocations()[self.relocations_range(from, to)]
+    }
+
+    pub fn remove_relocations_in(&mut self, from: u64, to: u64) -> Vec<Relocation>
+    {
+        let range = self.relocations_range(from, to);
+        match self
+        {
+            Header::Elf(header) => header.relocations.drain(range).collect(),
+            Header::PE(header) => header.relocations.drain(range).collect(),
+            Header::None => Vec::new(),
+        }
+    }
+
+    pub fn physical_to_virtual_address(&self, physical_address: u64) -> Option<u64>
+    {
+        self.get_sections()
+            .iter()
+            .find(|x| physical_address >= x.address && physical_address < x.address + x.size)
+            .map(|x| x.virtual_address + physical_address - x.address)
+    }
+
     pub fn virtual_to_physical_address(&self, virtual_address: u64) -> Option<u64>
     {
         self.get_sections()
@@ -3,6 +3,8 @@ use std::{collections::HashMap, fs::File, rc::Rc};
 use object::{pe::ImageNtHeaders64, read::pe::PeFile, LittleEndian, Object, ObjectSymbol};
 use pdb::FallibleIterator;
 
+use super::header::{Fixup, Relocation, SymbolLocation};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Section
 {
@@ -25,7 +27,9 @@ pub struct PEHeader
     pub bitness: u32,
     pub section_table: Vec<Section>,
     pub symbol_table: Rc<HashMap<u64, String>>,
-    pub inverse_symbol_table: HashMap<String, u64>
+    pub inverse_symbol_table: HashMap<String, u64>,
+    pub symbol_locations: HashMap<u64, SymbolLocation>,
+    pub relocations: Vec<Relocation>,
 }
 
 impl PEHeader
@@ -61,9 +65,26 @@ impl PEHeader
                 }
 
                 let mut symbols = HashMap::new();
+                let mut symbol_locations = HashMap::new();
+                let file_header = &header.nt_headers().file_header;
+                let symbol_table_offset = file_header.pointer_to_symbol_table.get(LittleEndian) as u64;
+                let string_table_offset = symbol_table_offset + file_header.number_of_symbols.get(LittleEndian) as u64 * 18;
                 for symbol in header.symbols()
                 {
-                    symbols.insert(symbol.address(), symbol.name().map(|s|s.to_string()).unwrap_or(format!("s_{:#x}", symbol.address())));
+                    let name = symbol.name().map(|s|s.to_string()).unwrap_or(format!("s_{:#x}", symbol.address()));
+                    let raw_name = &symbol.raw_symbol().name;
+                    // short names are stored in the symbol record, long names in the string table
+                    let location = if raw_name[..4] == [0, 0, 0, 0]
+                    {
+                        let offset = u32::from_le_bytes([raw_name[4], raw_name[5], raw_name[6], raw_name[7]]) as u64;
+                        SymbolLocation { offset: string_table_offset + offset, capacity: symbol.name_bytes().map(|n| n.len()).unwrap_or(0), name: name.clone() }
+                    }
+                    else
+                    {
+                        SymbolLocation { offset: symbol_table_offset + symbol.index().0 as u64 * 18, capacity: 8, name: name.clone() }
+                    };
+                    symbol_locations.insert(symbol.address(), location);
+                    symbols.insert(symbol.address(), name);
                 }
                 let mut pdb_file_path = None;
                 if let Some(data_dir) = header.data_directory(6)
@@ -118,6 +139,7 @@ impl PEHeader
                                                 {
                                                     let mangled_name = public_symbol.name.to_string().to_string();
                                                     let name = cpp_demangle::Symbol::new(&mangled_name);
+                                                    symbol_locations.remove(&(address.0 as u64));
                                                     if let Ok(name) = name
                                                     {
                                                         symbols.insert(address.0 as u64, name.to_string());
@@ -140,6 +162,9 @@ impl PEHeader
                 }
 
                 let inverse_symbol_table = symbols.iter().map(|(k, v)| (v.clone(), *k)).collect();
+                let relocations = header.data_directory(5)
+                    .map(|directory| Self::read_relocations(bytes, directory.virtual_address.get(LittleEndian), directory.size.get(LittleEndian), &section_table))
+                    .unwrap_or_default();
 
                 Some(PEHeader
                 {
@@ -147,13 +172,230 @@ impl PEHeader
                     bitness,
                     section_table,
                     symbol_table: Rc::new(symbols),
-                    inverse_symbol_table
+                    inverse_symbol_table,
+                    symbol_locations,
+                    relocations,
                 })
             },
             Err(_) => None,
         }
     }
 
+    fn rva_to_offset(section_table: &[Section], rva: u32) -> Option<usize>
+    {
+        section_table.iter()
+            .find(|section| rva >= section.virtual_address && section.virtual_address.checked_add(section.size_of_raw_data).is_some_and(|end| rva < end))
+            .and_then(|section| section.pointer_to_raw_data.checked_add(rva - section.virtual_address))
+            .map(|offset| offset as usize)
+    }
+
+    fn read_relocations(data: &[u8], directory_rva: u32, directory_size: u32, section_table: &[Section]) -> Vec<Relocation>
+    {
+        let mut relocations = Vec::new();
+        let start = match Self::rva_to_offset(section_table, directory_rva)
+        {
+            Some(start) => start,
+            None => return relocations,
+        };
+        let end = match start.checked_add(directory_size as usize)
+        {
+            Some(end) => end.min(data.len()),
+            None => return relocations,
+        };
+        let mut block = start;
+        while block + 8 <= end
+        {
+            let (page_rva, block_size) = (Self::read_u32(data, block).unwrap_or(0), Self::read_u32(data, block + 4).unwrap_or(0) as usize);
+            let block_end = match block.checked_add(block_size)
+            {
+                Some(block_end) if block_size >= 8 => block_end,
+                _ => break,
+            };
+            for entry in (block + 8..block_end.min(end)).step_by(2)
+            {
+                let value = Self::read_u16(data, entry).unwrap_or(0);
+                // IMAGE_REL_BASED_HIGHLOW and IMAGE_REL_BASED_DIR64, IMAGE_REL_BASED_ABSOLUTE is padding
+                let size = match value >> 12
+                {
+                    3 => 4,
+                    10 => 8,
+                    _ => continue,
+                };
+                let rva = match page_rva.checked_add((value & 0xFFF) as u32)
+                {
+                    Some(rva) => rva,
+                    None => continue,
+                };
+                if let Some(offset) = Self::rva_to_offset(section_table, rva)
+                {
+                    relocations.push(Relocation
+                    {
+                        offset: offset as u64,
+                        size,
+                        removal: Fixup
+                        {
+                            offset: entry as u64,
+                            bytes: vec![0, 0],
+                            description: format!("Removed base relocation at {:#X}", rva),
+                        },
+                    });
+                }
+            }
+            block = block_end;
+        }
+        relocations.sort_by_key(|relocation| relocation.offset);
+        relocations
+    }
+
+    fn read_u16(data: &[u8], offset: usize) -> Option<u16>
+    {
+        data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn read_u32(data: &[u8], offset: usize) -> Option<u32>
+    {
+        data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn checksum(data: &[u8], checksum_offset: usize) -> u32
+    {
+        let mut sum = 0u64;
+        for (i, word) in data.chunks(2).enumerate()
+        {
+            let offset = i * 2;
+            if offset >= checksum_offset && offset < checksum_offset + 4
+            {
+                continue;
+            }
+            let word = if word.len() == 2 { u16::from_le_bytes([word[0], word[1]]) } else { word[0] as u16 };
+            sum += word as u64;
+            sum = (sum & 0xFFFF) + (sum >> 16);
+        }
+        sum = (sum & 0xFFFF) + (sum >> 16);
+        (sum as u32).wrapping_add(data.len() as u32)
+    }
+
+    pub fn save_fixups(data: &[u8]) -> (Vec<Fixup>, Vec<String>)
+    {
+        let mut fixups = Vec::new();
+        let mut warnings = Vec::new();
+        let optional_header = match Self::read_u32(data, 0x3C)
+        {
+            Some(pe_offset) if data.get(pe_offset as usize..pe_offset as usize + 4) == Some(b"PE\0\0") => pe_offset as usize + 24,
+            _ =>
+            {
+                warnings.push("The PE signature is no longer valid".to_string());
+                return (fixups, warnings);
+            }
+        };
+        let data_directories = match Self::read_u16(data, optional_header)
+        {
+            Some(0x10B) => optional_header + 96,
+            Some(0x20B) => optional_header + 112,
+            _ =>
+            {
+                warnings.push("The PE optional header magic is no longer valid".to_string());
+                return (fixups, warnings);
+            }
+        };
+
+        let checksum_offset = optional_header + 64;
+        if let Some(old_checksum) = Self::read_u32(data, checksum_offset)
+        {
+            // a zero checksum means that the loader does not check it
+            let checksum = Self::checksum(data, checksum_offset);
+            if old_checksum != 0 && old_checksum != checksum
+            {
+                fixups.push(Fixup {
+                    offset: checksum_offset as u64,
+                    bytes: checksum.to_le_bytes().to_vec(),
+                    description: format!("Updated PE checksum from {:#X} to {:#X}", old_checksum, checksum),
+                });
+            }
+        }
+
+        let security_directory = data_directories + 4 * 8;
+        if let (Some(certificates_offset), Some(certificates_size)) = (Self::read_u32(data, security_directory), Self::read_u32(data, security_directory + 4))
+        {
+            if certificates_size != 0
+            {
+                warnings.push("The file has an Authenticode signature, it is invalidated by the changes".to_string());
+                if certificates_offset as u64 + certificates_size as u64 > data.len() as u64
+                {
+                    warnings.push("The certificate table points outside the file".to_string());
+                }
+            }
+        }
+        (fixups, warnings)
+    }
+
+    // appends a new executable section, the section table is not moved so there must be a free slot before the first section data
+    pub fn add_section(data: &[u8], name: &str, size: u64) -> Result<(Vec<u8>, u64), String>
+    {
+        if name.is_empty() || name.len() > 8
+        {
+            return Err("PE section names must be between 1 and 8 bytes long".to_string());
+        }
+        let coff_header = match Self::read_u32(data, 0x3C)
+        {
+            Some(pe_offset) if data.get(pe_offset as usize..pe_offset as usize + 4) == Some(b"PE\0\0") => pe_offset as usize + 4,
+            _ => return Err("The PE signature is not valid".to_string()),
+        };
+        let optional_header = coff_header + 20;
+        let number_of_sections = Self::read_u16(data, coff_header + 2).ok_or("The COFF header is outside the file")? as usize;
+        let size_of_optional_header = Self::read_u16(data, coff_header + 16).ok_or("The COFF header is outside the file")? as usize;
+        let read = |offset: usize| Self::read_u32(data, offset).ok_or("The optional header is outside the file".to_string());
+        let size_of_code = read(optional_header + 4)?;
+        let section_alignment = read(optional_header + 32)?.max(1) as u64;
+        let file_alignment = read(optional_header + 36)?.max(1) as u64;
+        let size_of_headers = read(optional_header + 60)? as usize;
+
+        let section_table = optional_header + size_of_optional_header;
+        let new_entry = section_table + number_of_sections * 40;
+        let mut headers_end = size_of_headers;
+        let mut max_address = 0u64;
+        for i in 0..number_of_sections
+        {
+            let entry = section_table + i * 40;
+            let (virtual_size, virtual_address) = (read(entry + 8)? as u64, read(entry + 12)? as u64);
+            let (size_of_raw_data, pointer_to_raw_data) = (read(entry + 16)? as u64, read(entry + 20)? as usize);
+            max_address = max_address.max(virtual_address + virtual_size.max(size_of_raw_data));
+            if pointer_to_raw_data != 0 && size_of_raw_data != 0
+            {
+                headers_end = headers_end.min(pointer_to_raw_data);
+            }
+        }
+        // SizeOfHeaders can be larger than a truncated file
+        if new_entry + 40 > headers_end.min(data.len()) || data[new_entry..new_entry + 40].iter().any(|b| *b != 0)
+        {
+            return Err("There is no space left in the section table".to_string());
+        }
+
+        let virtual_address = max_address.next_multiple_of(section_alignment);
+        let pointer_to_raw_data = (data.len() as u64).next_multiple_of(file_alignment);
+        let size_of_raw_data = size.next_multiple_of(file_alignment);
+        let size_of_image = (virtual_address + size).next_multiple_of(section_alignment);
+        if size_of_image > u32::MAX as u64 || pointer_to_raw_data + size_of_raw_data > u32::MAX as u64
+        {
+            return Err("The new section does not fit in a PE file".to_string());
+        }
+
+        let mut new_data = data.to_vec();
+        let mut write = |offset: usize, value: u32| new_data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        write(new_entry + 8, size as u32);
+        write(new_entry + 12, virtual_address as u32);
+        write(new_entry + 16, size_of_raw_data as u32);
+        write(new_entry + 20, pointer_to_raw_data as u32);
+        // IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ
+        write(new_entry + 36, 0x60000020);
+        write(optional_header + 4, size_of_code.wrapping_add(size_of_raw_data as u32));
+        write(optional_header + 56, size_of_image as u32);
+        new_data[new_entry..new_entry + name.len()].copy_from_slice(name.as_bytes());
+        new_data[coff_header + 2..coff_header + 4].copy_from_slice(&(number_of_sections as u16 + 1).to_le_bytes());
+        new_data.resize((pointer_to_raw_data + size_of_raw_data) as usize, 0);
+        Ok((new_data, pointer_to_raw_data))
+    }
+
     pub fn get_symbols(&self) -> Rc<HashMap<u64, String>>
     {
         self.symbol_table.clone()
@@ -1,17 +1,38 @@
 use clap::Parser;
-use crossterm::{event::{DisableMouseCapture, EnableMouseCapture}, execute, terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}};
+use crossterm::{event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture}, execute, terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}};
 use hex_patch::{app::App, args};
-use ratatui::backend::CrosstermBackend;
+use ratatui::backend::{CrosstermBackend, TestBackend};
 
 fn main() {
 
     let args = args::Args::parse();
 
+    if let Some(script) = args.script
+    {
+        let mut terminal = ratatui::Terminal::new(TestBackend::new(80, 24)).expect("Failed to create terminal");
+        let mut app = match App::new(args.file, &mut terminal)
+        {
+            Ok(app) => app,
+            Err(err) =>
+            {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        };
+        if let Err(err) = app.run_headless(&script)
+        {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     enable_raw_mode().expect("Failed to enable raw mode");
     let mut stdout = std::io::stdout();
     execute!(stdout,
         EnterAlternateScreen, 
-        EnableMouseCapture
+        EnableMouseCapture,
+        EnableBracketedPaste
     ).expect("Failed to execute setup commands");
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = ratatui::Terminal::new(backend).expect("Failed to create terminal");
@@ -26,7 +47,8 @@ fn main() {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     ).expect("Failed to execute teardown commands");
     terminal.show_cursor().expect("Failed to show cursor");
 